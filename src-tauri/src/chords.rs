@@ -0,0 +1,401 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::hotkeys::{parse_hotkey, HotkeyAction};
+
+// How long the second stroke is waited for after the leader
+const CHORD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+lazy_static! {
+    static ref MACHINE: Mutex<ChordMachine> = Mutex::new(ChordMachine::default());
+}
+
+/// A leader shortcut followed by a second key, e.g. Ctrl+Alt+T then E
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HotkeyChord {
+    pub leader: String,
+    pub key: String,
+    pub action: HotkeyAction,
+    // Replaces the configured target language for translate actions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_language: Option<String>,
+}
+
+/// Registration result of a chord's leader
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChordStatus {
+    pub leader: String,
+    pub key: String,
+    pub registered: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct ParsedChord {
+    leader: Shortcut,
+    key: Shortcut,
+    chord: HotkeyChord,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Pending {
+    leader: Shortcut,
+    generation: u64,
+}
+
+// Tracks whether a leader was pressed and which chord the next stroke completes. The
+// OS registration happens in the functions below, so this part can be tested on its own
+#[derive(Default)]
+struct ChordMachine {
+    chords: Vec<ParsedChord>,
+    leaders: Vec<Shortcut>, // Registered with the OS
+    keys: Vec<Shortcut>,    // Second keys registered for the pending chord
+    pending: Option<Pending>,
+    generation: u64,
+}
+
+impl ChordMachine {
+    // Second strokes for the pending chord, plus Escape to cancel it
+    fn pending_keys(&self) -> Vec<Shortcut> {
+        let Some(pending) = self.pending else {
+            return Vec::new();
+        };
+        let mut keys: Vec<Shortcut> = self
+            .chords
+            .iter()
+            .filter(|chord| chord.leader == pending.leader)
+            .map(|chord| chord.key)
+            .collect();
+        let escape = Shortcut::new(None, Code::Escape);
+        if !keys.contains(&escape) {
+            keys.push(escape);
+        }
+        keys.dedup();
+        keys
+    }
+
+    // Starts waiting for the second stroke, replacing a chord that was still pending
+    fn start(&mut self, leader: Shortcut) -> u64 {
+        self.generation += 1;
+        self.pending = Some(Pending {
+            leader,
+            generation: self.generation,
+        });
+        self.generation
+    }
+
+    // Ends the pending chord and returns the one `key` completes, if any
+    fn press(&mut self, key: Shortcut) -> Option<HotkeyChord> {
+        let pending = self.pending.take()?;
+        self.chords
+            .iter()
+            .find(|chord| chord.leader == pending.leader && chord.key == key)
+            .map(|chord| chord.chord.clone())
+    }
+
+    // Remembers the second keys registered for the chord started as `generation`. If that
+    // chord already ended, the keys are handed back to be unregistered right away
+    fn track(&mut self, generation: u64, keys: Vec<Shortcut>) -> Vec<Shortcut> {
+        match self.pending {
+            Some(pending) if pending.generation == generation => {
+                self.keys.extend(keys);
+                Vec::new()
+            }
+            _ => keys,
+        }
+    }
+
+    // Second keys to unregister; a key that failed to register may belong to someone else
+    fn release_keys(&mut self) -> Vec<Shortcut> {
+        std::mem::take(&mut self.keys)
+    }
+
+    // Cancels the chord started as `generation` if it is still pending
+    fn expire(&mut self, generation: u64) -> bool {
+        match self.pending {
+            Some(pending) if pending.generation == generation => {
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn unregister_all(app: &AppHandle, shortcuts: &[Shortcut]) {
+    for shortcut in shortcuts {
+        if let Err(e) = app.global_shortcut().unregister(*shortcut) {
+            log::warn!("Failed to unregister chord shortcut: {}", e);
+        }
+    }
+}
+
+// Registering and unregistering shortcuts waits for the main thread, so none of it
+// happens while MACHINE is locked
+fn on_leader(app: &AppHandle, leader: Shortcut) {
+    let (stale_keys, generation, keys) = {
+        let mut machine = MACHINE.lock().unwrap();
+        let stale_keys = machine.release_keys();
+        let generation = machine.start(leader);
+        (stale_keys, generation, machine.pending_keys())
+    };
+    unregister_all(app, &stale_keys);
+
+    let mut registered_keys = Vec::new();
+    for key in keys {
+        let registered = app
+            .global_shortcut()
+            .on_shortcut(key, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn_blocking(move || on_second_stroke(&app, key));
+                }
+            });
+        match registered {
+            Ok(()) => registered_keys.push(key),
+            Err(e) => log::warn!("Failed to register chord key: {}", e),
+        }
+    }
+    let orphaned = MACHINE.lock().unwrap().track(generation, registered_keys);
+    unregister_all(app, &orphaned);
+    log::info!("Chord started, waiting for the second key");
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(CHORD_TIMEOUT);
+        let expired = {
+            let mut machine = MACHINE.lock().unwrap();
+            machine.expire(generation).then(|| machine.release_keys())
+        };
+        if let Some(keys) = expired {
+            unregister_all(&app, &keys);
+            log::info!("Chord timed out");
+        }
+    });
+}
+
+fn on_second_stroke(app: &AppHandle, key: Shortcut) {
+    let (keys, chord) = {
+        let mut machine = MACHINE.lock().unwrap();
+        (machine.release_keys(), machine.press(key))
+    };
+    unregister_all(app, &keys);
+
+    let Some(chord) = chord else {
+        log::info!("Chord cancelled");
+        return;
+    };
+    log::info!("Chord completed: {:?}", chord.action);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        crate::run_hotkey_action(&app, chord.action, chord.target_language).await;
+    });
+}
+
+// A chord whose leader or second key is also a configured binding would take the
+// shortcut from that binding, or lose it to the binding while the chord is pending
+fn check_bindings(
+    bound: &[(HotkeyAction, Shortcut)],
+    leader: Shortcut,
+    key: Shortcut,
+) -> Result<(), String> {
+    let used_by = |shortcut: Shortcut| {
+        bound
+            .iter()
+            .find(|(_, bound)| *bound == shortcut)
+            .map(|(action, _)| action.label())
+    };
+    if let Some(action) = used_by(leader) {
+        return Err(format!("Leader is already used for {}", action));
+    }
+    if let Some(action) = used_by(key) {
+        return Err(format!("Second key is already used for {}", action));
+    }
+    Ok(())
+}
+
+/// Replaces the configured chords and registers their leaders. Second keys are only
+/// registered while a chord is pending, so they stay usable the rest of the time.
+/// Chords that use a shortcut from `bindings` are rejected
+pub fn apply_chords(
+    app: &AppHandle,
+    chords: &[HotkeyChord],
+    bindings: &BTreeMap<HotkeyAction, String>,
+) -> Vec<ChordStatus> {
+    let released = {
+        let mut machine = MACHINE.lock().unwrap();
+        let mut released = machine.release_keys();
+        released.append(&mut machine.leaders);
+        machine.pending = None;
+        machine.chords.clear();
+        released
+    };
+    unregister_all(app, &released);
+
+    let bound: Vec<(HotkeyAction, Shortcut)> = bindings
+        .iter()
+        .filter_map(|(action, text)| Some((*action, parse_hotkey(text).ok()?)))
+        .collect();
+    let mut leaders: Vec<Shortcut> = Vec::new();
+    let mut parsed_chords = Vec::new();
+    let mut statuses = Vec::new();
+    for chord in chords {
+        let parsed = parse_hotkey(&chord.leader)
+            .map_err(|e| format!("Leader: {}", e))
+            .and_then(|leader| {
+                let key = parse_hotkey(&chord.key).map_err(|e| format!("Second key: {}", e))?;
+                check_bindings(&bound, leader, key)?;
+                Ok((leader, key))
+            });
+        let result = parsed.and_then(|(leader, key)| {
+            if !leaders.contains(&leader) {
+                app.global_shortcut()
+                    .on_shortcut(leader, move |app, _shortcut, event| {
+                        // Registering the second keys blocks on the main thread, so it
+                        // happens outside the shortcut handler
+                        if event.state == ShortcutState::Pressed {
+                            let app = app.clone();
+                            tauri::async_runtime::spawn_blocking(move || on_leader(&app, leader));
+                        }
+                    })
+                    .map_err(|e| {
+                        format!(
+                            "Leader could not be registered, another application may be using it ({})",
+                            e
+                        )
+                    })?;
+                leaders.push(leader);
+            }
+            parsed_chords.push(ParsedChord {
+                leader,
+                key,
+                chord: chord.clone(),
+            });
+            Ok(())
+        });
+
+        if let Err(error) = &result {
+            log::error!(
+                "Failed to register chord {} then {}: {}",
+                chord.leader,
+                chord.key,
+                error
+            );
+        }
+        statuses.push(ChordStatus {
+            leader: chord.leader.clone(),
+            key: chord.key.clone(),
+            registered: result.is_ok(),
+            error: result.err(),
+        });
+    }
+
+    let mut machine = MACHINE.lock().unwrap();
+    machine.leaders = leaders;
+    machine.chords = parsed_chords;
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine(chords: &[(&str, &str, &str)]) -> ChordMachine {
+        let chords = chords
+            .iter()
+            .map(|(leader, key, language)| ParsedChord {
+                leader: parse_hotkey(leader).unwrap(),
+                key: parse_hotkey(key).unwrap(),
+                chord: HotkeyChord {
+                    leader: leader.to_string(),
+                    key: key.to_string(),
+                    action: HotkeyAction::TranslateClipboard,
+                    target_language: Some(language.to_string()),
+                },
+            })
+            .collect();
+        ChordMachine {
+            chords,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn completes_chords_and_times_out() {
+        let mut machine = machine(&[
+            ("Ctrl+Alt+T", "E", "English"),
+            ("Ctrl+Alt+T", "N", "Norwegian"),
+            ("Ctrl+Alt+Y", "E", "Spanish"),
+        ]);
+        let leader = parse_hotkey("Ctrl+Alt+T").unwrap();
+        let key = |key: &str| parse_hotkey(key).unwrap();
+
+        assert!(machine.pending_keys().is_empty());
+        assert_eq!(machine.press(key("N")), None);
+
+        machine.start(leader);
+        assert_eq!(
+            machine.pending_keys(),
+            vec![key("E"), key("N"), key("Escape")]
+        );
+        let chord = machine.press(key("N")).unwrap();
+        assert_eq!(chord.target_language.as_deref(), Some("Norwegian"));
+        assert!(machine.pending_keys().is_empty());
+
+        // Escape cancels the chord; other keys are not registered and never reach it,
+        // and once cancelled a second key does not complete anything
+        machine.start(leader);
+        assert_eq!(machine.press(key("Escape")), None);
+        assert_eq!(machine.press(key("E")), None);
+
+        // Only the latest chord expires, an earlier timeout does nothing
+        let first = machine.start(leader);
+        let second = machine.start(parse_hotkey("Ctrl+Alt+Y").unwrap());
+        assert!(!machine.expire(first));
+        assert_eq!(machine.pending_keys(), vec![key("E"), key("Escape")]);
+        assert!(machine.expire(second));
+        assert!(machine.pending_keys().is_empty());
+    }
+
+    #[test]
+    fn releases_only_the_keys_it_registered() {
+        let mut machine = machine(&[("Ctrl+Alt+T", "E", "English")]);
+        let key = |key: &str| parse_hotkey(key).unwrap();
+
+        // Escape failed to register, e.g. because another binding holds it
+        let generation = machine.start(key("Ctrl+Alt+T"));
+        assert!(machine.track(generation, vec![key("E")]).is_empty());
+        assert_eq!(machine.release_keys(), vec![key("E")]);
+        assert!(machine.release_keys().is_empty());
+
+        // Keys registered after the chord ended are handed straight back
+        let stale = machine.start(key("Ctrl+Alt+T"));
+        machine.start(key("Ctrl+Alt+T"));
+        assert_eq!(machine.track(stale, vec![key("E")]), vec![key("E")]);
+        assert!(machine.release_keys().is_empty());
+    }
+
+    #[test]
+    fn rejects_chords_that_reuse_a_binding() {
+        let key = |key: &str| parse_hotkey(key).unwrap();
+        let bound = [
+            (HotkeyAction::TranslateClipboard, key("Ctrl+Alt+T")),
+            (HotkeyAction::ToggleWindow, key("F8")),
+        ];
+
+        assert!(check_bindings(&bound, key("Ctrl+Alt+Y"), key("E")).is_ok());
+        assert_eq!(
+            check_bindings(&bound, key("Ctrl+Alt+T"), key("E")).unwrap_err(),
+            "Leader is already used for Translate clipboard"
+        );
+        assert_eq!(
+            check_bindings(&bound, key("Ctrl+Alt+Y"), key("F8")).unwrap_err(),
+            "Second key is already used for Show or hide window"
+        );
+    }
+}
@@ -0,0 +1,557 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tokio::time::{sleep, Instant};
+
+lazy_static! {
+    // On X11 without a clipboard manager, arboard stops serving what it wrote once its
+    // last Clipboard is dropped, so one is kept for the whole run
+    static ref ARBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+}
+
+fn with_arboard<T>(
+    use_clipboard: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, arboard::Error> {
+    let mut slot = ARBOARD.lock().unwrap();
+    let clipboard = match slot.as_mut() {
+        Some(clipboard) => clipboard,
+        None => slot.insert(arboard::Clipboard::new()?),
+    };
+    use_clipboard(clipboard)
+}
+
+/// Decoded clipboard image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClipboardImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl ClipboardImage {
+    fn to_rgba_image(&self) -> Result<image::RgbaImage> {
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, self.rgba.clone())
+            .ok_or_else(|| anyhow::anyhow!("Clipboard image has the wrong size"))
+    }
+
+    /// Scales the image down, keeping its aspect ratio, so neither side exceeds `max_side`
+    pub fn fit_within(self, max_side: usize) -> Result<Self> {
+        let longest = self.width.max(self.height);
+        if longest <= max_side {
+            return Ok(self);
+        }
+        let scale = |side: usize| (side * max_side / longest).max(1);
+        let (width, height) = (scale(self.width), scale(self.height));
+        let resized = image::imageops::resize(
+            &self.to_rgba_image()?,
+            width as u32,
+            height as u32,
+            image::imageops::FilterType::Triangle,
+        );
+        Ok(Self {
+            width,
+            height,
+            rgba: resized.into_raw(),
+        })
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let image = self.to_rgba_image()?;
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+}
+
+/// The text, HTML and image the clipboard held before the app used it for an automated
+/// copy or paste. Only what can be written back together is restored, see `restore`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClipboardSnapshot {
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub image: Option<ClipboardImage>,
+}
+
+/// Clipboard access used by the capture and replace pipelines, so they can be tested
+/// without a desktop session
+pub trait ClipboardBackend {
+    /// Returns `None` when the clipboard holds no text
+    fn read_text(&self) -> Result<Option<String>>;
+    fn write_text(&self, text: &str) -> Result<()>;
+
+    /// Returns the HTML flavour that browsers and office applications copy next to the text
+    fn read_html(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Writes HTML together with its plain-text flavour for applications without HTML
+    fn write_html(&self, _html: &str, alt_text: &str) -> Result<()> {
+        self.write_text(alt_text)
+    }
+
+    /// Returns `None` when the clipboard holds no image
+    fn read_image(&self) -> Result<Option<ClipboardImage>> {
+        Ok(None)
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot> {
+        Ok(ClipboardSnapshot {
+            text: self.read_text()?,
+            ..Default::default()
+        })
+    }
+
+    fn restore(&self, snapshot: &ClipboardSnapshot) -> Result<()> {
+        self.write_text(snapshot.text.as_deref().unwrap_or(""))
+    }
+
+    /// A counter the OS increments on every clipboard change, where it keeps one
+    fn sequence_number(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether the owner marked the content as secret, as password managers do
+    fn is_concealed(&self) -> bool {
+        false
+    }
+}
+
+/// The system clipboard, through the clipboard manager plugin for text and arboard for
+/// the other formats
+pub struct AppClipboard {
+    app: AppHandle,
+}
+
+impl AppClipboard {
+    pub fn new(app: &AppHandle) -> Self {
+        Self { app: app.clone() }
+    }
+}
+
+// Missing formats are errors in arboard, only real failures are passed on
+fn optional<T>(result: Result<T, arboard::Error>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(arboard::Error::ContentNotAvailable | arboard::Error::ConversionFailure) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl ClipboardBackend for AppClipboard {
+    fn read_text(&self) -> Result<Option<String>> {
+        // An empty clipboard or one holding an image is reported as an error
+        match self.app.clipboard().read_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(e) => {
+                log::debug!("No text on the clipboard: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    fn write_text(&self, text: &str) -> Result<()> {
+        crate::clipboard_watcher::note_own_write(text);
+        self.app.clipboard().write_text(text)?;
+        Ok(())
+    }
+
+    fn read_html(&self) -> Result<Option<String>> {
+        optional(with_arboard(|clipboard| clipboard.get().html()))
+    }
+
+    fn write_html(&self, html: &str, alt_text: &str) -> Result<()> {
+        crate::clipboard_watcher::note_own_write(alt_text);
+        with_arboard(|clipboard| clipboard.set_html(html, Some(alt_text)))?;
+        Ok(())
+    }
+
+    fn read_image(&self) -> Result<Option<ClipboardImage>> {
+        let image = optional(with_arboard(|clipboard| clipboard.get_image()))?;
+        Ok(image.map(|image| ClipboardImage {
+            width: image.width,
+            height: image.height,
+            rgba: image.bytes.into_owned(),
+        }))
+    }
+
+    fn snapshot(&self) -> Result<ClipboardSnapshot> {
+        Ok(ClipboardSnapshot {
+            text: optional(with_arboard(|clipboard| clipboard.get_text()))?,
+            html: self.read_html()?,
+            image: self.read_image()?,
+        })
+    }
+
+    // arboard writes HTML together with its plain text but an image only on its own, so
+    // an image copied next to text is not restored
+    fn restore(&self, snapshot: &ClipboardSnapshot) -> Result<()> {
+        if let Some(text) = &snapshot.text {
+            crate::clipboard_watcher::note_own_write(text);
+        }
+        if snapshot.image.is_some() && (snapshot.text.is_some() || snapshot.html.is_some()) {
+            log::warn!("Restoring the clipboard text without the image copied next to it");
+        }
+        with_arboard(|clipboard| match snapshot {
+            ClipboardSnapshot {
+                html: Some(html),
+                text,
+                ..
+            } => clipboard.set_html(html.as_str(), text.as_deref()),
+            ClipboardSnapshot {
+                text: Some(text), ..
+            } => clipboard.set_text(text.as_str()),
+            ClipboardSnapshot {
+                image: Some(image), ..
+            } => clipboard.set_image(arboard::ImageData {
+                width: image.width,
+                height: image.height,
+                bytes: image.rgba.as_slice().into(),
+            }),
+            _ => clipboard.clear(),
+        })?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn sequence_number(&self) -> Option<u64> {
+        Some(u64::from(unsafe {
+            winapi::um::winuser::GetClipboardSequenceNumber()
+        }))
+    }
+
+    #[cfg(target_os = "windows")]
+    fn is_concealed(&self) -> bool {
+        use winapi::um::winuser::{IsClipboardFormatAvailable, RegisterClipboardFormatA};
+
+        CONCEALED_FORMATS.iter().any(|name| {
+            let Ok(name) = std::ffi::CString::new(*name) else {
+                return false;
+            };
+            unsafe { IsClipboardFormatAvailable(RegisterClipboardFormatA(name.as_ptr())) != 0 }
+        })
+    }
+
+    // Only X11 (and XWayland) is asked, arboard has no way to list the offered formats
+    #[cfg(target_os = "linux")]
+    fn is_concealed(&self) -> bool {
+        match x11_clipboard_targets() {
+            Ok(targets) => targets
+                .iter()
+                .any(|target| CONCEALED_FORMATS.contains(&target.as_str())),
+            Err(e) => {
+                log::debug!("Could not list the clipboard formats: {}", e);
+                false
+            }
+        }
+    }
+}
+
+// Formats password managers add next to the content to keep clipboard tools away: KDE
+// Klipper's hint on Linux, the clipboard history opt-outs on Windows
+const CONCEALED_FORMATS: [&str; 3] = [
+    "x-kde-passwordManagerHint",
+    "ExcludeClipboardContentFromMonitorProcessing",
+    "Clipboard Viewer Ignore",
+];
+
+#[cfg(target_os = "linux")]
+const X11_REPLY_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Asks the CLIPBOARD owner for its TARGETS, the names of the formats it offers
+#[cfg(target_os = "linux")]
+fn x11_clipboard_targets() -> Result<Vec<String>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, CreateWindowAux, WindowClass};
+    use x11rb::protocol::Event;
+
+    let (conn, screen) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+    let atom =
+        |name: &str| -> Result<u32> { Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom) };
+    let clipboard = atom("CLIPBOARD")?;
+    let targets = atom("TARGETS")?;
+    let property = atom("GPTRANSLATE_TARGETS")?;
+    conn.convert_selection(window, clipboard, targets, property, x11rb::CURRENT_TIME)?;
+    conn.flush()?;
+
+    let deadline = std::time::Instant::now() + X11_REPLY_TIMEOUT;
+    loop {
+        match conn.poll_for_event()? {
+            Some(Event::SelectionNotify(event)) if event.requestor == window => {
+                if event.property == x11rb::NONE {
+                    return Ok(Vec::new());
+                }
+                break;
+            }
+            Some(_) => {}
+            None if std::time::Instant::now() >= deadline => {
+                return Err(anyhow::anyhow!("The clipboard owner did not answer"));
+            }
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    }
+
+    let reply = conn
+        .get_property(true, window, property, AtomEnum::ATOM, 0, 1024)?
+        .reply()?;
+    let atoms: Vec<u32> = reply
+        .value32()
+        .map(|atoms| atoms.collect())
+        .unwrap_or_default();
+    let mut names = Vec::with_capacity(atoms.len());
+    for atom in atoms {
+        let reply = conn.get_atom_name(atom)?.reply()?;
+        names.push(String::from_utf8_lossy(&reply.name).into_owned());
+    }
+    Ok(names)
+}
+
+/// What is compared to notice a clipboard change: the OS counter where there is one,
+/// otherwise the text itself
+#[derive(Debug, PartialEq)]
+pub enum ChangeMarker {
+    Sequence(u64),
+    Text(Option<String>),
+}
+
+pub fn change_marker(clipboard: &impl ClipboardBackend) -> Result<ChangeMarker> {
+    match clipboard.sequence_number() {
+        Some(sequence) => Ok(ChangeMarker::Sequence(sequence)),
+        None => Ok(ChangeMarker::Text(clipboard.read_text()?)),
+    }
+}
+
+/// Waits until the clipboard differs from `before` and then holds still for one poll,
+/// since some applications fill it in steps after the copy shortcut. Returns whether it
+/// changed within `timeout`
+pub async fn wait_for_change(
+    clipboard: &impl ClipboardBackend,
+    before: &ChangeMarker,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> Result<bool> {
+    let deadline = Instant::now() + timeout;
+    let mut changed: Option<ChangeMarker> = None;
+    loop {
+        sleep(poll_interval).await;
+        let current = change_marker(clipboard)?;
+        if current != *before {
+            if changed.as_ref() == Some(&current) {
+                return Ok(true);
+            }
+            changed = Some(current);
+        }
+        if Instant::now() >= deadline {
+            if changed.is_some() {
+                log::debug!("Clipboard kept changing, reading it anyway");
+            }
+            return Ok(changed.is_some());
+        }
+    }
+}
+
+/// Where the hotkey takes the text to translate from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSelection {
+    #[default]
+    Clipboard,
+    Primary, // The highlighted text on Linux, no Ctrl+C needed
+    PrimaryThenClipboard,
+}
+
+/// The Linux PRIMARY selection, set by highlighting text under X11 and most Wayland
+/// compositors
+pub trait SelectionBackend {
+    /// Returns `None` when nothing is highlighted or the platform has no PRIMARY selection
+    fn read_primary(&self) -> Result<Option<String>>;
+}
+
+pub struct SystemSelection;
+
+impl SelectionBackend for SystemSelection {
+    #[cfg(target_os = "linux")]
+    fn read_primary(&self) -> Result<Option<String>> {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+
+        match with_arboard(|clipboard| {
+            clipboard
+                .get()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text()
+        }) {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read_primary(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Reads the text to capture from the configured source, skipping empty text
+pub fn read_capture_text(
+    clipboard: &impl ClipboardBackend,
+    selection: &impl SelectionBackend,
+    source: CaptureSelection,
+) -> Result<Option<String>> {
+    let non_empty = |text: Option<String>| text.filter(|text| !text.trim().is_empty());
+    match source {
+        CaptureSelection::Clipboard => Ok(non_empty(clipboard.read_text()?)),
+        CaptureSelection::Primary => Ok(non_empty(selection.read_primary()?)),
+        CaptureSelection::PrimaryThenClipboard => {
+            // A failing PRIMARY read, e.g. on a compositor without support, falls back too
+            let primary = selection.read_primary().unwrap_or_else(|e| {
+                log::warn!("Failed to read the PRIMARY selection: {}", e);
+                None
+            });
+            match non_empty(primary) {
+                Some(text) => Ok(Some(text)),
+                None => Ok(non_empty(clipboard.read_text()?)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FakeClipboard(Option<&'static str>);
+
+    impl ClipboardBackend for FakeClipboard {
+        fn read_text(&self) -> Result<Option<String>> {
+            Ok(self.0.map(String::from))
+        }
+
+        fn write_text(&self, _text: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct FakeSelection(Option<&'static str>);
+
+    impl SelectionBackend for FakeSelection {
+        fn read_primary(&self) -> Result<Option<String>> {
+            match self.0 {
+                Some("fail") => Err(anyhow::anyhow!("no PRIMARY support")),
+                text => Ok(text.map(String::from)),
+            }
+        }
+    }
+
+    #[test]
+    fn reads_the_configured_selection() {
+        let read = |clipboard, primary, source| {
+            read_capture_text(&FakeClipboard(clipboard), &FakeSelection(primary), source)
+        };
+
+        let both = (Some("copied"), Some("highlighted"));
+        assert_eq!(
+            read(both.0, both.1, CaptureSelection::Clipboard).unwrap(),
+            Some("copied".to_string())
+        );
+        assert_eq!(
+            read(both.0, both.1, CaptureSelection::PrimaryThenClipboard).unwrap(),
+            Some("highlighted".to_string())
+        );
+        assert_eq!(
+            read(
+                Some("copied"),
+                Some("  "),
+                CaptureSelection::PrimaryThenClipboard
+            )
+            .unwrap(),
+            Some("copied".to_string())
+        );
+        assert_eq!(
+            read(
+                Some("copied"),
+                Some("fail"),
+                CaptureSelection::PrimaryThenClipboard
+            )
+            .unwrap(),
+            Some("copied".to_string())
+        );
+        assert_eq!(
+            read(Some("copied"), None, CaptureSelection::Primary).unwrap(),
+            None
+        );
+        assert!(read(None, Some("fail"), CaptureSelection::Primary).is_err());
+    }
+
+    #[test]
+    fn scales_large_images_down() {
+        let image = |width, height| ClipboardImage {
+            width,
+            height,
+            rgba: vec![255; width * height * 4],
+        };
+        let small = image(40, 20).fit_within(100).unwrap();
+        assert_eq!((small.width, small.height), (40, 20));
+
+        let large = image(400, 100).fit_within(100).unwrap();
+        assert_eq!((large.width, large.height), (100, 25));
+        assert_eq!(large.rgba.len(), 100 * 25 * 4);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_clipboard_to_change_and_settle() {
+        // Each read returns the next text, the last one stays
+        struct FillingClipboard(Mutex<Vec<&'static str>>);
+
+        impl ClipboardBackend for FillingClipboard {
+            fn read_text(&self) -> Result<Option<String>> {
+                let mut texts = self.0.lock().unwrap();
+                let text = if texts.len() > 1 {
+                    texts.remove(0)
+                } else {
+                    texts[0]
+                };
+                Ok(Some(text.to_string()))
+            }
+
+            fn write_text(&self, _text: &str) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let poll = Duration::from_millis(1);
+        let before = ChangeMarker::Text(Some(String::new()));
+        let filling = FillingClipboard(Mutex::new(vec!["", "", "partial", "complete"]));
+        let changed = wait_for_change(&filling, &before, poll, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(changed);
+        assert_eq!(filling.read_text().unwrap().as_deref(), Some("complete"));
+
+        // The copy never arrived
+        let unchanged = FillingClipboard(Mutex::new(vec![""]));
+        let changed = wait_for_change(&unchanged, &before, poll, Duration::from_millis(5))
+            .await
+            .unwrap();
+        assert!(!changed);
+    }
+}
@@ -0,0 +1,320 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::clipboard::{AppClipboard, ClipboardBackend};
+use crate::translation::{self, CaptureSource};
+use crate::AppState;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Recent texts the app put on the clipboard itself, which are never translated
+const OWN_WRITES_KEPT: usize = 8;
+
+// Longest translation shown in a notification, the window shows all of it
+const NOTIFICATION_LENGTH: usize = 200;
+
+// Paused from the tray or a hotkey until resumed or the app restarts
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref OWN_WRITES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchResultDisplay {
+    #[default]
+    Notification,
+    Window,
+}
+
+/// Automatic translation of text copied in other applications
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ClipboardWatchSettings {
+    pub enabled: bool,
+    pub min_length: usize, // In characters, ignoring surrounding whitespace
+    pub max_length: usize,
+    pub debounce_ms: u64, // How long the clipboard must stay unchanged
+    pub show_result_in: WatchResultDisplay,
+}
+
+impl Default for ClipboardWatchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_length: 3,
+            max_length: 2000,
+            debounce_ms: 700,
+            show_result_in: WatchResultDisplay::default(),
+        }
+    }
+}
+
+/// Remembers text the app writes to the clipboard so the watcher skips it
+pub fn note_own_write(text: &str) {
+    let mut own_writes = OWN_WRITES.lock().unwrap();
+    own_writes.push_back(text.to_string());
+    while own_writes.len() > OWN_WRITES_KEPT {
+        own_writes.pop_front();
+    }
+}
+
+fn is_own_write(text: &str) -> bool {
+    OWN_WRITES.lock().unwrap().iter().any(|own| own == text)
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Pauses or resumes watching and returns whether it is now paused
+pub fn toggle_paused() -> bool {
+    let paused = !PAUSED.fetch_xor(true, Ordering::Relaxed);
+    log::info!(
+        "Clipboard watching {}",
+        if paused { "paused" } else { "resumed" }
+    );
+    paused
+}
+
+fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Reports clipboard text once it has stayed the same for the debounce time, so text
+// copied several times in a row is only translated once. Only fingerprints are kept,
+// as the clipboard may hold passwords
+#[derive(Default)]
+struct ChangeTracker {
+    last_seen: u64,
+    pending: Option<(u64, Instant)>,
+}
+
+impl ChangeTracker {
+    // Takes the current text as already seen, e.g. when starting or while paused
+    fn reset(&mut self, text: &str) {
+        self.last_seen = fingerprint(text);
+        self.pending = None;
+    }
+
+    // Whether the text differs from everything observed so far
+    fn is_new(&self, text: &str) -> bool {
+        let seen = fingerprint(text);
+        seen != self.last_seen && self.pending.map(|(pending, _)| pending) != Some(seen)
+    }
+
+    fn observe(&mut self, text: String, now: Instant, debounce: Duration) -> Option<String> {
+        let seen = fingerprint(&text);
+        if seen == self.last_seen {
+            self.pending = None;
+            return None;
+        }
+        match self.pending {
+            Some((pending, since)) if pending == seen => {
+                if now.duration_since(since) < debounce {
+                    return None;
+                }
+            }
+            _ => {
+                self.pending = Some((seen, now));
+                return None;
+            }
+        }
+        self.reset(&text);
+        Some(text)
+    }
+}
+
+fn accepts(text: &str, settings: &ClipboardWatchSettings) -> bool {
+    let length = text.trim().chars().count();
+    length >= settings.min_length && length <= settings.max_length && !is_own_write(text)
+}
+
+#[derive(Serialize, Clone)]
+struct WatchTranslation {
+    original_text: String,
+    translated_text: String,
+    detected_language: String,
+}
+
+fn truncate(text: &str, length: usize) -> String {
+    match text.char_indices().nth(length) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+async fn translate_copied_text(app: &AppHandle, text: String) {
+    let config = app.state::<AppState>().config.lock().await.clone();
+    let show_result_in = config.clipboard_watch.show_result_in;
+    let target_language = config.target_language.clone();
+    if crate::language::is_probably_in(&text, &target_language) {
+        log::info!("Copied text is already in {}", target_language);
+        return;
+    }
+
+    let response = match translation::translate_with_config(
+        text.clone(),
+        CaptureSource::Watcher,
+        config,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Failed to translate copied text: {}", e);
+            return;
+        }
+    };
+    // Text the local check could not place is recognised after translating and not shown
+    if crate::language::same_language(&response.detected_language, &target_language) {
+        log::info!("Copied text is already in {}", target_language);
+        return;
+    }
+
+    if let Err(e) = crate::history::add_translation_to_history(&response, None).await {
+        log::error!("Failed to add translation to history: {}", e);
+    }
+    crate::notify_budget_warning(app, response.budget_warning.as_deref());
+
+    match show_result_in {
+        WatchResultDisplay::Notification => {
+            if let Err(e) = app
+                .notification()
+                .builder()
+                .title(format!("Translated from {}", response.detected_language))
+                .body(truncate(&response.translated_text, NOTIFICATION_LENGTH))
+                .show()
+            {
+                log::error!("Failed to show translation notification: {}", e);
+            }
+        }
+        WatchResultDisplay::Window => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let event = WatchTranslation {
+                    original_text: text,
+                    translated_text: response.translated_text,
+                    detected_language: response.detected_language,
+                };
+                if let Err(e) = window.emit("watch-translation", event) {
+                    log::error!("Failed to emit watched translation: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Polls the clipboard and translates new text while watching is enabled and not paused
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let clipboard = AppClipboard::new(&app);
+        let read = || clipboard.read_text().ok().flatten().unwrap_or_default();
+        let mut tracker = ChangeTracker::default();
+        tracker.reset(&read());
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let settings = app
+                .state::<AppState>()
+                .config
+                .lock()
+                .await
+                .clipboard_watch
+                .clone();
+            // Text copied inside the app is meant for the app
+            let focused = app
+                .get_webview_window("main")
+                .and_then(|window| window.is_focused().ok())
+                .unwrap_or(false);
+            if !settings.enabled || is_paused() || focused {
+                tracker.reset(&read());
+                continue;
+            }
+
+            // Secrets are dropped before the tracker or anything else holds on to them.
+            // Listing the clipboard formats waits on the owner, off the async runtime
+            let text = read();
+            if tracker.is_new(&text) {
+                let owner = app.clone();
+                let concealed = tauri::async_runtime::spawn_blocking(move || {
+                    AppClipboard::new(&owner).is_concealed()
+                })
+                .await
+                .unwrap_or(false);
+                if concealed {
+                    log::info!("Skipping clipboard content marked as secret");
+                    tracker.reset(&text);
+                    continue;
+                }
+            }
+
+            let debounce = Duration::from_millis(settings.debounce_ms);
+            let Some(text) = tracker.observe(text, Instant::now(), debounce) else {
+                continue;
+            };
+            if !accepts(&text, &settings) {
+                continue;
+            }
+            translate_copied_text(&app, text).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_text_once_it_settles() {
+        let debounce = Duration::from_millis(500);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut tracker = ChangeTracker::default();
+        tracker.reset("already there");
+
+        assert_eq!(
+            tracker.observe("already there".into(), at(0), debounce),
+            None
+        );
+        assert!(!tracker.is_new("already there"));
+        assert!(tracker.is_new("first"));
+        assert_eq!(tracker.observe("first".into(), at(100), debounce), None);
+        // Checked for secrets once, not again on every poll while it settles
+        assert!(!tracker.is_new("first"));
+        // Copied again before settling, the debounce starts over
+        assert_eq!(tracker.observe("second".into(), at(300), debounce), None);
+        assert_eq!(tracker.observe("second".into(), at(700), debounce), None);
+        assert_eq!(
+            tracker.observe("second".into(), at(800), debounce),
+            Some("second".to_string())
+        );
+        assert_eq!(tracker.observe("second".into(), at(2000), debounce), None);
+    }
+
+    #[test]
+    fn skips_short_long_and_own_text() {
+        let settings = ClipboardWatchSettings {
+            min_length: 3,
+            max_length: 10,
+            ..Default::default()
+        };
+        assert!(accepts("  hallo  ", &settings));
+        assert!(!accepts(" ja ", &settings));
+        assert!(!accepts("dette er for langt", &settings));
+
+        note_own_write("oversatt");
+        assert!(!accepts("oversatt", &settings));
+    }
+}
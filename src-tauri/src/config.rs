@@ -1,9 +1,118 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
+use crate::chords::HotkeyChord;
+use crate::clipboard::CaptureSelection;
+use crate::clipboard_watcher::ClipboardWatchSettings;
+use crate::config_layers;
+use crate::hotkeys::{default_hotkeys, find_duplicates, parse_hotkey, HotkeyAction};
+use crate::ocr::{ImageReader, OcrSettings};
+use crate::paths;
+use crate::spending::{default_model_prices, BudgetLimits, ModelPrice};
+use crate::vault::{self, EncryptionHeader};
+
+const DEFAULT_CUSTOM_PROMPT: &str = "Translate the given text from {detected_language} to {target_language} accurately while preserving the meaning, tone, and nuance of the original content.\n\n# Additional Details\n- Ensure the translation retains the context, cultural meaning, tone, formal/informal style, and any idiomatic expressions.\n- Do **not** alter names, technical terms, or specific formatting unless required for grammatical correctness in the target language.\n- If the detected language is the same as the target language, choose the most appropriate alternative language for translation.\n\n# Output Format\nThe translation output should be provided as valid JSON containing 'detected_language' and 'translated_text' fields.\n\n# Notes\n- Ensure punctuation and capitalization match the norms of the target language.\n- When encountering idiomatic expressions, adapt them to equivalent phrases in the target language rather than direct word-for-word translation.\n- For ambiguous content, aim for the most contextually appropriate meaning.\n- Take into consideration the whole text and what it is about.";
+
+struct Migration {
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+// Ordered schema migrations; entry N upgrades a config from version N to N + 1.
+// Files written before versioning was introduced are treated as version 0.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "replace the old gpt-4o-mini default model with gpt-4.1-nano",
+        apply: migrate_default_model,
+    },
+    Migration {
+        description: "drop source_language and the automatic target language",
+        apply: migrate_target_language,
+    },
+    Migration {
+        description: "add custom_prompt and alternative_target_language",
+        apply: migrate_prompt_fields,
+    },
+    Migration {
+        description: "replace the single hotkey with per-action hotkeys",
+        apply: migrate_hotkeys,
+    },
+];
+
+const THEMES: &[&str] = &["auto", "light", "dark"];
+
+pub const CURRENT_CONFIG_VERSION: u32 = MIGRATIONS.len() as u32;
+
+fn schema_version(config: &Map<String, Value>) -> u32 {
+    config
+        .get("config_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32
+}
+
+fn migrate(config: &mut Map<String, Value>, version: u32) {
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log::info!(
+            "Migrating config to schema version {}: {}",
+            index + 1,
+            migration.description
+        );
+        (migration.apply)(config);
+    }
+    config.insert(
+        "config_version".to_string(),
+        Value::from(CURRENT_CONFIG_VERSION),
+    );
+}
+
+fn migrate_default_model(config: &mut Map<String, Value>) {
+    // Only configs from before the prompt settings existed still carry the old default
+    let legacy = !config.contains_key("custom_prompt")
+        || !config.contains_key("alternative_target_language");
+    match config.get("model").and_then(Value::as_str) {
+        Some("gpt-4o-mini") if legacy => {}
+        Some(_) => return,
+        None => {}
+    }
+    config.insert("model".to_string(), Value::from("gpt-4.1-nano"));
+}
+
+fn migrate_target_language(config: &mut Map<String, Value>) {
+    config.remove("source_language");
+    match config.get("target_language").and_then(Value::as_str) {
+        Some(language) if language != "auto" => {}
+        _ => {
+            config.insert("target_language".to_string(), Value::from("English"));
+        }
+    }
+}
+
+fn migrate_prompt_fields(config: &mut Map<String, Value>) {
+    config
+        .entry("custom_prompt")
+        .or_insert_with(|| Value::from(DEFAULT_CUSTOM_PROMPT));
+    config
+        .entry("alternative_target_language")
+        .or_insert_with(|| Value::from("Norwegian"));
+}
+
+fn migrate_hotkeys(config: &mut Map<String, Value>) {
+    let Some(hotkey) = config.remove("hotkey") else {
+        return;
+    };
+    let mut hotkeys = serde_json::to_value(default_hotkeys()).unwrap_or_default();
+    hotkeys["translate_clipboard"] = hotkey;
+    config.insert("hotkeys".to_string(), hotkeys);
+}
+
+// Missing fields fall back to their defaults so older or hand-edited files still load
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct Config {
+    pub config_version: u32,  // Schema version, see MIGRATIONS
     pub api_provider: String, // "openai" or "azure_openai"
     pub openai_api_key: String,
     pub azure_endpoint: String,
@@ -14,15 +123,45 @@ pub struct Config {
     pub target_language: String, // User-specified target language (e.g., "Spanish", "French", "German")
     pub alternative_target_language: String, // Used when detected language is same as target language
     pub auto_start: bool,
-    pub hotkey: String,
+    pub hotkeys: BTreeMap<HotkeyAction, String>, // Empty shortcuts leave the action unbound
+    pub hotkey_chords: Vec<HotkeyChord>,
+    pub clipboard_watch: ClipboardWatchSettings,
+    pub capture_selection: CaptureSelection, // PRIMARY only exists on Linux
+    pub ocr: OcrSettings,
+    pub image_reader: ImageReader,
     pub theme: String, // "auto", "light", "dark"
     pub minimize_to_tray: bool,
     pub custom_prompt: String, // Now supports variables: {detected_language}, {target_language}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionHeader>, // Set when secrets and history are encrypted at rest
+    pub model_prices: BTreeMap<String, ModelPrice>, // Keyed by model or deployment name
+    pub budget: BudgetLimits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>, // Name of the profile in profiles.json these settings came from
+    #[serde(flatten)]
+    pub extra: Map<String, Value>, // Fields this version does not know about, kept on save
+}
+
+/// A problem with a single config field, reported by `Config::validate`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConfigProblem {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: CURRENT_CONFIG_VERSION,
             api_provider: "openai".to_string(),
             openai_api_key: "".to_string(),
             azure_endpoint: "".to_string(),
@@ -33,104 +172,461 @@ impl Default for Config {
             target_language: "English".to_string(), // Default target language
             alternative_target_language: "Norwegian".to_string(), // Default alternative target language
             auto_start: true,
-            hotkey: "CommandOrControl+Alt+C".to_string(),
+            hotkeys: default_hotkeys(),
+            hotkey_chords: Vec::new(),
+            clipboard_watch: ClipboardWatchSettings::default(),
+            capture_selection: CaptureSelection::default(),
+            ocr: OcrSettings::default(),
+            image_reader: ImageReader::default(),
             theme: "auto".to_string(),
             minimize_to_tray: true,
-            custom_prompt: "Translate the given text from {detected_language} to {target_language} accurately while preserving the meaning, tone, and nuance of the original content.\n\n# Additional Details\n- Ensure the translation retains the context, cultural meaning, tone, formal/informal style, and any idiomatic expressions.\n- Do **not** alter names, technical terms, or specific formatting unless required for grammatical correctness in the target language.\n- If the detected language is the same as the target language, choose the most appropriate alternative language for translation.\n\n# Output Format\nThe translation output should be provided as valid JSON containing 'detected_language' and 'translated_text' fields.\n\n# Notes\n- Ensure punctuation and capitalization match the norms of the target language.\n- When encountering idiomatic expressions, adapt them to equivalent phrases in the target language rather than direct word-for-word translation.\n- For ambiguous content, aim for the most contextually appropriate meaning.\n- Take into consideration the whole text and what it is about.".to_string(),
+            custom_prompt: DEFAULT_CUSTOM_PROMPT.to_string(),
+            encryption: None,
+            model_prices: default_model_prices(),
+            budget: BudgetLimits::default(),
+            active_profile: None,
+            extra: Map::new(),
         }
     }
 }
 
 impl Config {
     pub fn get_config_dir() -> Result<PathBuf> {
-        let home_dir =
-            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        let config_dir = home_dir.join(".gptranslate");
-
-        if !config_dir.exists() {
-            std::fs::create_dir_all(&config_dir)?;
-        }
-
-        Ok(config_dir)
+        paths::config_dir()
     }
 
     pub fn get_config_path() -> Result<PathBuf> {
         Ok(Self::get_config_dir()?.join("config.json"))
     }
+    /// Loads the config, running any pending schema migrations. An unreadable file
+    /// is never replaced with defaults; it is set aside and the error returned instead.
+    /// A missing file gives the defaults, which are only written by the next save
     pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
-
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-
-            // Try to parse the config, and if it fails due to missing fields, migrate it
-            match serde_json::from_str::<Config>(&content) {
-                Ok(config) => Ok(config),
-                Err(_) => {
-                    // Try to parse as a generic Value first to preserve existing settings
-                    if let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&content) {
-                        // Add missing fields with defaults
-                        if !value.get("custom_prompt").is_some() {
-                            value["custom_prompt"] = serde_json::Value::String(
-                                "Translate the given text from {detected_language} to {target_language} accurately while preserving the meaning, tone, and nuance of the original content.\n\n# Additional Details\n- Ensure the translation retains the context, cultural meaning, tone, formal/informal style, and any idiomatic expressions.\n- Do **not** alter names, technical terms, or specific formatting unless required for grammatical correctness in the target language.\n- If the detected language is the same as the target language, choose the most appropriate alternative language for translation.\n\n# Output Format\nThe translation output should be provided as valid JSON containing 'detected_language' and 'translated_text' fields.\n\n# Notes\n- Ensure punctuation and capitalization match the norms of the target language.\n- When encountering idiomatic expressions, adapt them to equivalent phrases in the target language rather than direct word-for-word translation.\n- For ambiguous content, aim for the most contextually appropriate meaning.\n- Take into consideration the whole text and what it is about.".to_string()
-                            );
-                        }
-
-                        // Add alternative_target_language if missing
-                        if !value.get("alternative_target_language").is_some() {
-                            value["alternative_target_language"] =
-                                serde_json::Value::String("Norwegian".to_string());
-                        }
-
-                        // Update model default to gpt-4.1-nano if it's the old default
-                        if let Some(model) = value.get("model") {
-                            if model.as_str() == Some("gpt-4o-mini") {
-                                value["model"] =
-                                    serde_json::Value::String("gpt-4.1-nano".to_string());
-                            }
-                        } else {
-                            value["model"] = serde_json::Value::String("gpt-4.1-nano".to_string());
-                        }
-
-                        // Remove old source_language field if it exists
-                        if value.get("source_language").is_some() {
-                            value.as_object_mut().unwrap().remove("source_language");
-                        }
-
-                        // Ensure target_language has a sensible default if it was "auto"
-                        if let Some(target_lang) = value.get("target_language") {
-                            if target_lang.as_str() == Some("auto") {
-                                value["target_language"] =
-                                    serde_json::Value::String("English".to_string());
-                            }
-                        } else {
-                            value["target_language"] =
-                                serde_json::Value::String("English".to_string());
-                        }
-
-                        // Try to parse again with the migrated config
-                        let migrated_config: Config = serde_json::from_value(value)?;
-                        migrated_config.save()?; // Save the migrated config
-                        Ok(migrated_config)
-                    } else {
-                        // If all else fails, use default config
-                        let default_config = Self::default();
-                        default_config.save()?;
-                        Ok(default_config)
-                    }
-                }
+        Self::load_from(&Self::get_config_path()?)
+    }
+
+    fn load_from(config_path: &Path) -> Result<Self> {
+        // Editors that save by deleting and renaming leave the file missing for a moment,
+        // so defaults written here could replace the user's settings
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(config_path)?;
+        let mut value = match serde_json::from_str::<Value>(&content) {
+            Ok(Value::Object(map)) => map,
+            Ok(_) => {
+                return Err(Self::reject_config(
+                    config_path,
+                    "it does not contain a JSON object",
+                ))
+            }
+            Err(e) => {
+                return Err(Self::reject_config(
+                    config_path,
+                    &format!("it is not valid JSON ({})", e),
+                ))
             }
-        } else {
-            let default_config = Self::default();
-            default_config.save()?;
-            Ok(default_config)
+        };
+
+        let version = schema_version(&value);
+        if version > CURRENT_CONFIG_VERSION {
+            log::warn!(
+                "config.json uses schema version {} but this build only knows version {}, newer settings may be ignored",
+                version,
+                CURRENT_CONFIG_VERSION
+            );
+        }
+
+        let needs_migration = version < CURRENT_CONFIG_VERSION;
+        if needs_migration {
+            let backup_path = config_path.with_file_name(format!("config.v{}.bak.json", version));
+            std::fs::copy(config_path, &backup_path)?;
+            log::info!(
+                "Backed up config.json to {:?} before migrating",
+                backup_path
+            );
+
+            migrate(&mut value, version);
+        }
+
+        let config: Config = serde_json::from_value(Value::Object(value)).map_err(|e| {
+            Self::reject_config(
+                config_path,
+                &format!("its settings could not be read ({})", e),
+            )
+        })?;
+        if needs_migration {
+            config.save_to(config_path)?;
+        }
+        Ok(config)
+    }
+
+    /// Builds a config from settings written by any schema version, e.g. an imported bundle
+    pub fn from_settings(mut value: Map<String, Value>) -> Result<Self> {
+        let version = schema_version(&value);
+        if version < CURRENT_CONFIG_VERSION {
+            migrate(&mut value, version);
+        }
+        Ok(serde_json::from_value(Value::Object(value))?)
+    }
+
+    // Keeps a copy of a config that failed to load so a later save cannot destroy it
+    fn reject_config(config_path: &Path, reason: &str) -> anyhow::Error {
+        let mut message = format!("Could not load config.json because {}", reason);
+        let copy = config_path.with_file_name(format!(
+            "config.invalid-{}.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let preserved = std::fs::copy(config_path, &copy).map(|_| copy);
+        match preserved {
+            Ok(copy) => message.push_str(&format!(". A copy was kept at {}", copy.display())),
+            Err(e) => log::error!("Failed to keep a copy of the invalid config: {}", e),
         }
+        anyhow::anyhow!(message)
     }
 
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::get_config_path()?;
-        let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&config_path, content)?;
+        self.save_to(&Self::get_config_path()?)
+    }
+
+    fn save_to(&self, config_path: &Path) -> Result<()> {
+        // Values coming from the policy file, environment or command line stay out of
+        // config.json. The vault owns the encryption header once it is set up, so a config
+        // coming from the frontend can never drop it by accident
+        let mut stored = config_layers::saved_form(self)?;
+        stored.encryption = vault::header().or_else(|| self.encryption.clone());
+        // A file from a newer build keeps its version, so that build does not migrate it again
+        stored.config_version = CURRENT_CONFIG_VERSION.max(self.config_version);
+        vault::encrypt_secrets(stored.secrets_mut())?;
+
+        // Written next to config.json and renamed over it, so the file watcher and a
+        // crash mid-write never see a half-written config
+        let content = serde_json::to_string_pretty(&stored)?;
+        let temp_path = config_path.with_extension("json.tmp");
+        std::fs::write(&temp_path, content)?;
+        std::fs::rename(&temp_path, config_path)?;
         Ok(())
     }
+
+    /// Checks the settings that would make translations or the hotkey fail at runtime
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        match self.api_provider.as_str() {
+            "openai" => {
+                if self.openai_api_key.trim().is_empty() {
+                    problems.push(ConfigProblem::new(
+                        "openai_api_key",
+                        "An OpenAI API key is required",
+                    ));
+                }
+                if self.model.trim().is_empty() {
+                    problems.push(ConfigProblem::new("model", "A model must be selected"));
+                }
+            }
+            "azure_openai" => {
+                if self.azure_api_key.trim().is_empty() {
+                    problems.push(ConfigProblem::new(
+                        "azure_api_key",
+                        "An Azure OpenAI API key is required",
+                    ));
+                }
+                if let Err(message) = validate_azure_endpoint(&self.azure_endpoint) {
+                    problems.push(ConfigProblem::new("azure_endpoint", message));
+                }
+                if self.azure_deployment_name.trim().is_empty() {
+                    problems.push(ConfigProblem::new(
+                        "azure_deployment_name",
+                        "A deployment name is required",
+                    ));
+                }
+                if self.azure_api_version.trim().is_empty() {
+                    problems.push(ConfigProblem::new(
+                        "azure_api_version",
+                        "An API version is required",
+                    ));
+                }
+            }
+            other => problems.push(ConfigProblem::new(
+                "api_provider",
+                format!("Unknown API provider \"{}\"", other),
+            )),
+        }
+
+        for (action, hotkey) in &self.hotkeys {
+            if hotkey.trim().is_empty() {
+                continue;
+            }
+            if let Err(e) = parse_hotkey(hotkey) {
+                problems.push(ConfigProblem::new(
+                    "hotkeys",
+                    format!("{}: {}", action.label(), e),
+                ));
+            }
+        }
+        for chord in &self.hotkey_chords {
+            let leader = parse_hotkey(&chord.leader);
+            let second = parse_hotkey(&chord.key);
+            let message = match (&leader, &second) {
+                (Err(e), _) => Some(format!("Leader {:?}: {}", chord.leader, e)),
+                (_, Err(e)) => Some(format!("Second key {:?}: {}", chord.key, e)),
+                _ => None,
+            };
+            if let Some(message) = message {
+                problems.push(ConfigProblem::new("hotkey_chords", message));
+            }
+            let taken_by = self.hotkeys.iter().find(
+                |(_, hotkey)| matches!((parse_hotkey(hotkey), &leader), (Ok(a), Ok(b)) if a == *b),
+            );
+            if let Some((action, _)) = taken_by {
+                problems.push(ConfigProblem::new(
+                    "hotkey_chords",
+                    format!(
+                        "Leader {} is already the hotkey for {}",
+                        chord.leader,
+                        action.label().to_lowercase()
+                    ),
+                ));
+            }
+        }
+        for (action, first) in find_duplicates(&self.hotkeys) {
+            problems.push(ConfigProblem::new(
+                "hotkeys",
+                format!(
+                    "{} uses the same hotkey as {}",
+                    action.label(),
+                    first.label().to_lowercase()
+                ),
+            ));
+        }
+
+        if !THEMES.contains(&self.theme.as_str()) {
+            problems.push(ConfigProblem::new(
+                "theme",
+                format!(
+                    "Unknown theme \"{}\", expected one of {}",
+                    self.theme,
+                    THEMES.join(", ")
+                ),
+            ));
+        }
+
+        if self.target_language.trim().is_empty() {
+            problems.push(ConfigProblem::new(
+                "target_language",
+                "A target language is required",
+            ));
+        }
+
+        problems
+    }
+
+    fn secrets_mut(&mut self) -> [&mut String; 2] {
+        [&mut self.openai_api_key, &mut self.azure_api_key]
+    }
+
+    /// Replaces encrypted API keys with their plaintext; requires an unlocked vault
+    pub fn decrypt_secrets(&mut self) -> Result<()> {
+        vault::decrypt_secrets(self.secrets_mut())
+    }
+}
+
+fn validate_azure_endpoint(endpoint: &str) -> std::result::Result<(), String> {
+    if endpoint.trim().is_empty() {
+        return Err("An Azure OpenAI endpoint is required".to_string());
+    }
+    let url = url::Url::parse(endpoint.trim())
+        .map_err(|e| format!("The endpoint is not a valid URL: {}", e))?;
+    if url.scheme() != "https" {
+        return Err("The endpoint must use https".to_string());
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err("The endpoint must include a host name".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gptranslate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("config.json")
+    }
+
+    fn files_starting_with(path: &Path, prefix: &str) -> Vec<PathBuf> {
+        std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|file| file.unwrap().path())
+            .filter(|file| {
+                file.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with(prefix)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn migrates_an_unversioned_config_and_keeps_a_backup() {
+        let path = config_path();
+        let legacy = r#"{
+            "openai_api_key": "sk-test",
+            "model": "gpt-4o-mini",
+            "source_language": "auto",
+            "target_language": "auto",
+            "hotkey": "Ctrl+Alt+T"
+        }"#;
+        std::fs::write(&path, legacy).unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.config_version, CURRENT_CONFIG_VERSION);
+        assert_eq!(config.model, "gpt-4.1-nano");
+        assert_eq!(config.target_language, "English");
+        assert_eq!(config.alternative_target_language, "Norwegian");
+        assert_eq!(
+            config.hotkeys[&HotkeyAction::TranslateClipboard],
+            "Ctrl+Alt+T"
+        );
+        assert!(!config.extra.contains_key("source_language"));
+        assert!(!config.extra.contains_key("hotkey"));
+
+        let backup = path.with_file_name("config.v0.bak.json");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), legacy);
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["config_version"], CURRENT_CONFIG_VERSION);
+
+        // Loading the migrated file again is a no-op
+        Config::load_from(&path).unwrap();
+        assert_eq!(files_starting_with(&path, "config.v").len(), 1);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_config_gives_defaults_without_writing_them() {
+        let path = config_path();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.model, Config::default().model);
+        assert!(!path.exists());
+
+        config.save_to(&path).unwrap();
+        assert!(path.exists());
+        assert!(!path.with_extension("json.tmp").exists());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_unreadable_configs_and_keeps_a_copy() {
+        let path = config_path();
+        for contents in ["{ \"model\": ", "[1, 2]", r#"{ "auto_start": "yes" }"#] {
+            std::fs::write(&path, contents).unwrap();
+            let error = Config::load_from(&path).unwrap_err().to_string();
+            assert!(error.contains("A copy was kept"), "{}", error);
+            // The file itself is never replaced with defaults
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        }
+        assert!(!files_starting_with(&path, "config.invalid-").is_empty());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn loads_a_newer_config_without_migrating_it() {
+        let path = config_path();
+        let newer = CURRENT_CONFIG_VERSION + 1;
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{ "config_version": {}, "future_setting": true }}"#,
+                newer
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.config_version, newer);
+        assert!(files_starting_with(&path, "config.v").is_empty());
+
+        config.save_to(&path).unwrap();
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["config_version"], newer);
+        assert_eq!(saved["future_setting"], true);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    fn fields(problems: &[ConfigProblem]) -> Vec<&str> {
+        problems
+            .iter()
+            .map(|problem| problem.field.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn validates_provider_hotkeys_and_theme() {
+        let mut config = Config {
+            openai_api_key: "sk-test".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.validate(), Vec::new());
+
+        config.openai_api_key = " ".to_string();
+        config.theme = "sepia".to_string();
+        config
+            .hotkeys
+            .insert(HotkeyAction::ToggleWindow, "Ctrl+Nope".to_string());
+        assert_eq!(
+            fields(&config.validate()),
+            vec!["openai_api_key", "hotkeys", "theme"]
+        );
+
+        let azure = |endpoint: &str| Config {
+            api_provider: "azure_openai".to_string(),
+            azure_api_key: "key".to_string(),
+            azure_endpoint: endpoint.to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            azure("https://example.openai.azure.com/").validate(),
+            Vec::new()
+        );
+        for endpoint in [
+            "",
+            "example.openai.azure.com",
+            "http://example.openai.azure.com",
+        ] {
+            assert_eq!(fields(&azure(endpoint).validate()), vec!["azure_endpoint"]);
+        }
+
+        let unknown = Config {
+            api_provider: "anthropic".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(fields(&unknown.validate()), vec!["api_provider"]);
+    }
+
+    #[test]
+    fn keeps_unknown_fields_and_fills_in_missing_ones() {
+        let path = config_path();
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{ "config_version": {}, "theme": "dark", "window": {{ "width": 640 }} }}"#,
+                CURRENT_CONFIG_VERSION
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.theme, "dark");
+        assert_eq!(config.model, Config::default().model);
+        assert_eq!(config.extra["window"]["width"], 640);
+
+        config.save_to(&path).unwrap();
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["window"]["width"], 640);
+        assert_eq!(saved["theme"], "dark");
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,326 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::Config;
+
+// Environment variables are named after the config field, e.g. GPTRANSLATE_OPENAI_API_KEY
+const ENV_PREFIX: &str = "GPTRANSLATE_";
+
+// Fields managed by the app itself that can never be overridden
+const PROTECTED_FIELDS: &[&str] = &["config_version", "encryption"];
+
+/// Where the effective value of a config field came from, lowest precedence first
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigLayer {
+    Default,
+    File,
+    Policy,
+    Env,
+    Cli,
+}
+
+#[derive(Default)]
+struct Overrides {
+    values: Map<String, Value>,
+    layers: BTreeMap<String, ConfigLayer>,
+}
+
+impl Overrides {
+    fn set(&mut self, key: &str, value: Value, layer: ConfigLayer) {
+        self.values.insert(key.to_string(), value);
+        self.layers.insert(key.to_string(), layer);
+    }
+}
+
+lazy_static! {
+    // Policy, environment and command line values are read once at startup
+    static ref OVERRIDES: Overrides = collect_overrides();
+    // Values the overridden fields had in config.json, written back instead of the overrides
+    static ref FILE_VALUES: Mutex<Map<String, Value>> = Mutex::new(Map::new());
+    static ref SOURCES: Mutex<BTreeMap<String, ConfigLayer>> = Mutex::new(BTreeMap::new());
+}
+
+/// System-wide policy file that administrators can use to pin settings for every user
+pub fn get_policy_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        let program_data =
+            std::env::var_os("ProgramData").unwrap_or_else(|| "C:\\ProgramData".into());
+        PathBuf::from(program_data)
+            .join("GPTranslate")
+            .join("policy.json")
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        PathBuf::from("/Library/Application Support/GPTranslate/policy.json")
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        PathBuf::from("/etc/gptranslate/policy.json")
+    }
+}
+
+fn default_fields() -> Map<String, Value> {
+    match serde_json::to_value(Config::default()) {
+        Ok(Value::Object(mut fields)) => {
+            fields.retain(|key, _| !PROTECTED_FIELDS.contains(&key.as_str()));
+            // Optional fields are skipped when empty but can still be overridden
+            fields.entry("active_profile").or_insert(Value::Null);
+            fields
+        }
+        _ => Map::new(),
+    }
+}
+
+// Converts a text value from the environment or command line to the field's JSON type
+fn parse_value(key: &str, raw: &str, default: &Value) -> Result<Value> {
+    let value = match default {
+        Value::Bool(_) => match raw.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Value::Bool(true),
+            "0" | "false" | "no" | "off" => Value::Bool(false),
+            _ => return Err(anyhow::anyhow!("{} expects true or false", key)),
+        },
+        Value::String(_) | Value::Null => Value::String(raw.to_string()),
+        _ => serde_json::from_str(raw)
+            .map_err(|e| anyhow::anyhow!("{} expects a JSON value: {}", key, e))?,
+    };
+    Ok(value)
+}
+
+fn read_policy(fields: &Map<String, Value>, overrides: &mut Overrides) -> Result<()> {
+    let path = get_policy_path();
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let contents = std::fs::read_to_string(&path)?;
+    let policy: Map<String, Value> = serde_json::from_str(&contents)?;
+    for (key, value) in policy {
+        if fields.contains_key(&key) {
+            overrides.set(&key, value, ConfigLayer::Policy);
+        } else {
+            log::warn!("Ignoring unknown setting {:?} in {:?}", key, path);
+        }
+    }
+    log::info!("Applied config policy from {:?}", path);
+    Ok(())
+}
+
+fn read_env(fields: &Map<String, Value>, overrides: &mut Overrides) {
+    for (key, default) in fields {
+        let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+        let Ok(raw) = std::env::var(&name) else {
+            continue;
+        };
+        match parse_value(&name, &raw, default) {
+            Ok(value) => overrides.set(key, value, ConfigLayer::Env),
+            Err(e) => log::warn!("Ignoring environment override: {}", e),
+        }
+    }
+}
+
+// Accepts `--openai-api-key VALUE` and `--openai-api-key=VALUE`; other flags such as
+// `--autostart` are left alone
+fn read_args(
+    args: impl IntoIterator<Item = String>,
+    fields: &Map<String, Value>,
+    overrides: &mut Overrides,
+) {
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            continue;
+        };
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        let key = name.replace('-', "_");
+        let Some(default) = fields.get(&key) else {
+            continue;
+        };
+
+        let raw = match inline_value {
+            Some(value) => value,
+            None => match args.next_if(|next| !next.starts_with("--")) {
+                Some(value) => value,
+                // A bare boolean flag switches the setting on
+                None if default.is_boolean() => "true".to_string(),
+                None => {
+                    log::warn!("Ignoring --{} without a value", name);
+                    continue;
+                }
+            },
+        };
+        match parse_value(&format!("--{}", name), &raw, default) {
+            Ok(value) => overrides.set(&key, value, ConfigLayer::Cli),
+            Err(e) => log::warn!("Ignoring command line override: {}", e),
+        }
+    }
+}
+
+fn collect_overrides() -> Overrides {
+    let fields = default_fields();
+    let mut overrides = Overrides::default();
+
+    if let Err(e) = read_policy(&fields, &mut overrides) {
+        log::error!(
+            "Failed to read config policy {:?}: {}",
+            get_policy_path(),
+            e
+        );
+    }
+    read_env(&fields, &mut overrides);
+    read_args(std::env::args().skip(1), &fields, &mut overrides);
+
+    if !overrides.layers.is_empty() {
+        log::info!(
+            "Config values overridden outside config.json: {:?}",
+            overrides.layers
+        );
+    }
+    overrides
+}
+
+fn merge_overrides(config: &Config) -> Result<Config> {
+    let mut value = serde_json::to_value(config)?;
+    if let Value::Object(fields) = &mut value {
+        for (key, override_value) in &OVERRIDES.values {
+            fields.insert(key.clone(), override_value.clone());
+        }
+    }
+    serde_json::from_value(value).map_err(|e| anyhow::anyhow!("Invalid config override: {}", e))
+}
+
+/// Loads config.json and layers the policy file, environment and command line on top
+pub fn load() -> Result<Config> {
+    let file_config = Config::load()?;
+    apply(file_config, true)
+}
+
+/// Layers the overrides on top of the built-in defaults, used when config.json is unreadable
+pub fn load_defaults() -> Config {
+    apply(Config::default(), false).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        Config::default()
+    })
+}
+
+fn apply(file_config: Config, from_file: bool) -> Result<Config> {
+    // A missing config.json loads as the defaults and sets no keys of its own
+    let config_path = Config::get_config_path()?;
+    let file_keys: Vec<String> = if from_file && config_path.exists() {
+        let contents = std::fs::read_to_string(&config_path)?;
+        serde_json::from_str::<Map<String, Value>>(&contents)?
+            .keys()
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let file_fields = match serde_json::to_value(&file_config)? {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    let config = merge_overrides(&file_config)?;
+
+    let mut sources = BTreeMap::new();
+    for key in default_fields().keys() {
+        let layer = OVERRIDES.layers.get(key).copied().unwrap_or_else(|| {
+            if file_keys.contains(key) {
+                ConfigLayer::File
+            } else {
+                ConfigLayer::Default
+            }
+        });
+        sources.insert(key.clone(), layer);
+    }
+
+    let mut file_values = FILE_VALUES.lock().unwrap();
+    file_values.clear();
+    for key in OVERRIDES.values.keys() {
+        if let Some(value) = file_fields.get(key) {
+            file_values.insert(key.clone(), value.clone());
+        }
+    }
+    *SOURCES.lock().unwrap() = sources;
+
+    Ok(config)
+}
+
+/// Puts the overrides back on a config built from saved settings, e.g. a profile
+pub fn reapply(config: &Config) -> Result<Config> {
+    if OVERRIDES.values.is_empty() {
+        return Ok(config.clone());
+    }
+    merge_overrides(config)
+}
+
+/// Replaces overridden values with what config.json had, so overrides are never saved.
+/// A field that was changed away from its override value is kept as is.
+pub fn restore_file_values(fields: &mut Map<String, Value>) {
+    let file_values = FILE_VALUES.lock().unwrap();
+    for (key, override_value) in &OVERRIDES.values {
+        if fields.get(key) != Some(override_value) {
+            continue;
+        }
+        match file_values.get(key) {
+            Some(value) => fields.insert(key.clone(), value.clone()),
+            None => fields.remove(key),
+        };
+    }
+}
+
+/// Returns the config without any override values, as it should be persisted
+pub fn saved_form(config: &Config) -> Result<Config> {
+    if OVERRIDES.values.is_empty() {
+        return Ok(config.clone());
+    }
+    let mut value = serde_json::to_value(config)?;
+    if let Value::Object(fields) = &mut value {
+        restore_file_values(fields);
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+pub fn sources() -> BTreeMap<String, ConfigLayer> {
+    SOURCES.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_command_line_overrides() {
+        let fields = default_fields();
+        let mut overrides = Overrides::default();
+        let args = [
+            "--autostart",
+            "--openai-api-key",
+            "sk-test",
+            "--model=gpt-4.1",
+            "--minimize-to-tray",
+            "false",
+            "--auto-start",
+        ]
+        .map(String::from);
+        read_args(args, &fields, &mut overrides);
+
+        assert_eq!(overrides.values["openai_api_key"], "sk-test");
+        assert_eq!(overrides.values["model"], "gpt-4.1");
+        assert_eq!(overrides.values["minimize_to_tray"], false);
+        assert_eq!(overrides.values["auto_start"], true);
+        assert!(!overrides.values.contains_key("autostart"));
+        assert_eq!(overrides.layers["model"], ConfigLayer::Cli);
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::config::Config;
+
+// Editors and dotfile managers often write a file several times in a row
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches config.json and reloads it after it was changed on disk
+pub fn start(app: AppHandle) -> Result<()> {
+    let config_path = Config::get_config_path()?;
+    let config_name = config_path.file_name().map(|name| name.to_owned());
+    let config_dir = Config::get_config_dir()?;
+    let (sender, receiver) = mpsc::channel();
+
+    // The directory is watched rather than the file, since many editors save by
+    // replacing the file, which would end a watch on the file itself
+    let mut watcher =
+        notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+            Ok(event) => {
+                let touches_config = event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == config_name.as_deref());
+                if touches_config && !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = sender.send(());
+                }
+            }
+            Err(e) => log::error!("Config watcher error: {}", e),
+        })?;
+    watcher.watch(&config_dir, RecursiveMode::NonRecursive)?;
+
+    std::thread::Builder::new()
+        .name("config-watcher".to_string())
+        .spawn(move || {
+            // Keeps the watcher alive for as long as the thread runs
+            let _watcher = watcher;
+            while receiver.recv().is_ok() {
+                while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+                // A delete is followed by the write that replaces the file, which is
+                // reloaded instead
+                if !config_path.exists() {
+                    continue;
+                }
+
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    crate::reload_config(&app).await;
+                });
+            }
+        })?;
+
+    log::info!("Watching {:?} for config changes", config_dir);
+    Ok(())
+}
@@ -1,8 +1,38 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::paths;
+use crate::translation::{TranslationMetadata, TranslationResponse};
+use crate::vault;
+
+type HistoryJob = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    // All access to history.json goes through one thread so read-modify-write cycles
+    // from concurrent translations cannot interleave and lose entries
+    static ref HISTORY_ACTOR: mpsc::Sender<HistoryJob> = {
+        let (sender, receiver) = mpsc::channel::<HistoryJob>();
+        std::thread::Builder::new()
+            .name("history-actor".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    job();
+                }
+            })
+            .expect("failed to spawn history actor thread");
+        sender
+    };
+}
+
+// Keep only the most recent entries to prevent the file from growing too large
+pub const MAX_HISTORY_ENTRIES: usize = 100;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TranslationEntry {
@@ -12,9 +42,22 @@ pub struct TranslationEntry {
     pub detected_language: String,
     pub target_language: String,
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: Option<TranslationMetadata>, // Missing on entries recorded before metadata was tracked
+    #[serde(default)]
+    pub parent_id: Option<String>, // Entry this one was re-translated from
+    #[serde(default)]
+    pub revisions: Vec<TranslationRevision>, // Earlier versions of translated_text, oldest first
+}
+
+/// A translation that was replaced by a human correction
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TranslationRevision {
+    pub translated_text: String,
+    pub replaced_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct TranslationHistory {
     pub entries: Vec<TranslationEntry>,
 }
@@ -30,77 +73,324 @@ impl TranslationHistory {
         // Insert at the beginning to keep newest entries first
         self.entries.insert(0, entry);
 
-        // Keep only the last 100 entries to prevent the file from growing too large
-        if self.entries.len() > 100 {
-            self.entries.truncate(100);
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            self.entries.truncate(MAX_HISTORY_ENTRIES);
         }
     }
 }
 
 fn get_history_file_path() -> Result<PathBuf> {
-    let home_dir =
-        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(paths::data_dir()?.join("history.json"))
+}
+
+fn get_backup_file_path(history_path: &Path) -> PathBuf {
+    history_path.with_extension("json.bak")
+}
+
+// Runs a job on the history actor and waits for its result without blocking the
+// async runtime
+async fn run_on_actor<T, F>(job: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    HISTORY_ACTOR
+        .send(Box::new(move || {
+            let _ = reply_sender.send(job());
+        }))
+        .map_err(|_| anyhow::anyhow!("History actor is not running"))?;
+    reply_receiver
+        .await
+        .map_err(|_| anyhow::anyhow!("History actor stopped before replying"))?
+}
+
+enum HistoryFile {
+    Missing,
+    Valid(TranslationHistory),
+    Corrupt(anyhow::Error),
+}
 
-    let config_dir = home_dir.join(".gptranslate");
+// I/O errors and a locked vault are returned as errors; only damaged contents count as corrupt
+fn read_history_file(path: &Path) -> Result<HistoryFile> {
+    if !path.exists() {
+        return Ok(HistoryFile::Missing);
+    }
 
-    // Create the directory if it doesn't exist
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)?;
+    let mut contents = fs::read_to_string(path)?;
+    if vault::is_encrypted(&contents) {
+        if !vault::status().unlocked {
+            return Err(anyhow::anyhow!("Encrypted storage is locked"));
+        }
+        contents = match vault::decrypt(&contents) {
+            Ok(contents) => contents,
+            Err(e) => return Ok(HistoryFile::Corrupt(e)),
+        };
     }
 
-    Ok(config_dir.join("history.json"))
+    match serde_json::from_str(&contents) {
+        Ok(history) => Ok(HistoryFile::Valid(history)),
+        Err(e) => Ok(HistoryFile::Corrupt(e.into())),
+    }
 }
 
-pub fn load_history() -> Result<TranslationHistory> {
-    let history_path = get_history_file_path()?;
+fn write_history_file(history_path: &Path, history: &TranslationHistory) -> Result<()> {
+    let mut contents = serde_json::to_string_pretty(history)?;
+    if vault::is_enabled() {
+        contents = vault::encrypt(&contents)?;
+    }
+
+    // Write the new contents next to the real file first, so a crash mid-write
+    // leaves the previous history untouched
+    let temp_path = history_path.with_extension("json.tmp");
+    {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
 
-    if !history_path.exists() {
-        return Ok(TranslationHistory::new());
+    // Only a readable file replaces the backup, a damaged one would overwrite the copy
+    // recovery depends on
+    match read_history_file(history_path) {
+        Ok(HistoryFile::Valid(_)) => {
+            fs::copy(history_path, get_backup_file_path(history_path))?;
+        }
+        Ok(HistoryFile::Missing) => {}
+        Ok(HistoryFile::Corrupt(e)) => {
+            log::warn!("Not backing up the damaged history file: {}", e);
+        }
+        Err(e) => log::warn!("Not backing up the unreadable history file: {}", e),
     }
+    fs::rename(&temp_path, history_path)?;
+    Ok(())
+}
+
+fn read_history_from_disk(history_path: &Path) -> Result<TranslationHistory> {
+    let backup_path = get_backup_file_path(history_path);
 
-    let contents = fs::read_to_string(history_path)?;
-    let history: TranslationHistory =
-        serde_json::from_str(&contents).unwrap_or_else(|_| TranslationHistory::new());
+    match read_history_file(history_path)? {
+        HistoryFile::Valid(history) => Ok(history),
+        HistoryFile::Missing => match read_history_file(&backup_path) {
+            Ok(HistoryFile::Valid(history)) => {
+                log::warn!("History file is missing, restoring it from backup");
+                write_history_file(history_path, &history)?;
+                Ok(history)
+            }
+            _ => Ok(TranslationHistory::new()),
+        },
+        HistoryFile::Corrupt(e) => {
+            log::error!("History file is corrupted: {}", e);
 
-    Ok(history)
+            // Keep the damaged file around for inspection instead of overwriting it
+            let corrupt_path = history_path.with_file_name(format!(
+                "history.corrupt-{}.json",
+                Utc::now().format("%Y%m%d%H%M%S")
+            ));
+            fs::rename(history_path, &corrupt_path)?;
+
+            match read_history_file(&backup_path)? {
+                HistoryFile::Valid(history) => {
+                    log::warn!(
+                        "Recovered history from backup, damaged file kept at {}",
+                        corrupt_path.display()
+                    );
+                    write_history_file(history_path, &history)?;
+                    Ok(history)
+                }
+                _ => Err(anyhow::anyhow!(
+                    "History file is corrupted and no valid backup exists, damaged file kept at {}",
+                    corrupt_path.display()
+                )),
+            }
+        }
+    }
 }
 
-pub fn save_history(history: &TranslationHistory) -> Result<()> {
-    let history_path = get_history_file_path()?;
-    let contents = serde_json::to_string_pretty(history)?;
-    fs::write(history_path, contents)?;
-    Ok(())
+pub async fn load_history() -> Result<TranslationHistory> {
+    run_on_actor(|| read_history_from_disk(&get_history_file_path()?)).await
 }
 
-pub fn add_translation_to_history(
-    original_text: String,
-    translated_text: String,
-    detected_language: String,
-    target_language: String,
-) -> Result<()> {
-    let mut history = load_history()?;
+/// Replaces the stored history as a whole
+pub async fn save_history(history: &TranslationHistory) -> Result<()> {
+    let history = history.clone();
+    run_on_actor(move || write_history_file(&get_history_file_path()?, &history)).await
+}
+
+/// Loads, changes and saves the history as one step on the history actor
+pub async fn modify_history<T, F>(change: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut TranslationHistory) -> Result<T> + Send + 'static,
+{
+    run_on_actor(move || {
+        let history_path = get_history_file_path()?;
+        let mut history = read_history_from_disk(&history_path)?;
+        let result = change(&mut history)?;
+        write_history_file(&history_path, &history)?;
+        Ok(result)
+    })
+    .await
+}
 
+pub async fn add_translation_to_history(
+    response: &TranslationResponse,
+    parent_id: Option<String>,
+) -> Result<TranslationEntry> {
     let entry = TranslationEntry {
         id: uuid::Uuid::new_v4().to_string(),
-        original_text,
-        translated_text,
-        detected_language,
-        target_language,
+        original_text: response.original_text.clone(),
+        translated_text: response.translated_text.clone(),
+        detected_language: response.detected_language.clone(),
+        target_language: response.target_language.clone(),
         timestamp: Utc::now(),
+        metadata: Some(response.metadata.clone()),
+        parent_id,
+        revisions: Vec::new(),
     };
 
-    history.add_entry(entry);
-    save_history(&history)?;
+    let added = entry.clone();
+    modify_history(move |history| {
+        let counted = entry.clone();
+        history.add_entry(entry);
+        // Statistics outlive the history limit, a failure there does not lose the entry
+        if let Err(e) = crate::stats::record_usage(&counted, &history.entries) {
+            log::error!("Failed to record translation usage: {}", e);
+        }
+        Ok(())
+    })
+    .await?;
 
-    Ok(())
+    Ok(added)
 }
 
-pub fn get_translation_history() -> Result<TranslationHistory> {
+pub async fn get_history_entry(id: &str) -> Result<TranslationEntry> {
     load_history()
+        .await?
+        .entries
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| anyhow::anyhow!("History entry not found: {}", id))
+}
+
+pub async fn update_translation_in_history(
+    id: &str,
+    translated_text: String,
+) -> Result<TranslationEntry> {
+    let id = id.to_string();
+    modify_history(move |history| {
+        let entry = history
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| anyhow::anyhow!("History entry not found: {}", id))?;
+
+        if entry.translated_text != translated_text {
+            // Keep the text being replaced so the machine output and every correction stay visible
+            let previous = std::mem::replace(&mut entry.translated_text, translated_text);
+            entry.revisions.push(TranslationRevision {
+                translated_text: previous,
+                replaced_at: Utc::now(),
+            });
+        }
+
+        Ok(entry.clone())
+    })
+    .await
 }
 
-pub fn clear_translation_history() -> Result<()> {
+pub async fn get_translation_history() -> Result<TranslationHistory> {
+    load_history().await
+}
+
+pub async fn clear_translation_history() -> Result<()> {
     let history = TranslationHistory::new();
-    save_history(&history)?;
+    save_history(&history).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str) -> TranslationEntry {
+        TranslationEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            original_text: text.to_string(),
+            translated_text: text.to_uppercase(),
+            detected_language: "Norwegian".to_string(),
+            target_language: "English".to_string(),
+            timestamp: Utc::now(),
+            metadata: None,
+            parent_id: None,
+            revisions: Vec::new(),
+        }
+    }
+
+    fn history_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gptranslate-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("history.json")
+    }
+
+    fn texts(history: &TranslationHistory) -> Vec<&str> {
+        history
+            .entries
+            .iter()
+            .map(|entry| entry.original_text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn recovers_a_corrupt_file_from_the_backup() {
+        let path = history_path();
+        let mut history = TranslationHistory::new();
+        history.add_entry(entry("hei"));
+        write_history_file(&path, &history).unwrap();
+        history.add_entry(entry("takk"));
+        write_history_file(&path, &history).unwrap();
+
+        fs::write(&path, "{\"entries\": [").unwrap();
+        let recovered = read_history_from_disk(&path).unwrap();
+
+        // The backup holds the history from before the last save
+        assert_eq!(texts(&recovered), vec!["hei"]);
+        let kept: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|file| file.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with("history.corrupt-"))
+            .collect();
+        assert_eq!(kept.len(), 1);
+        // The recovered history was written back in place of the damaged file
+        assert!(matches!(
+            read_history_file(&path).unwrap(),
+            HistoryFile::Valid(_)
+        ));
+
+        fs::write(&path, "not json").unwrap();
+        fs::write(get_backup_file_path(&path), "not json either").unwrap();
+        assert!(read_history_from_disk(&path).is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn restores_a_missing_file_and_keeps_a_valid_backup() {
+        let path = history_path();
+        let mut history = TranslationHistory::new();
+        history.add_entry(entry("hei"));
+        write_history_file(&path, &history).unwrap();
+        write_history_file(&path, &history).unwrap();
+
+        // A damaged file is not rotated over the good backup
+        fs::write(&path, "not json").unwrap();
+        history.add_entry(entry("takk"));
+        write_history_file(&path, &history).unwrap();
+        let backup = read_history_file(&get_backup_file_path(&path)).unwrap();
+        assert!(matches!(&backup, HistoryFile::Valid(backup) if texts(backup) == vec!["hei"]));
+
+        fs::remove_file(&path).unwrap();
+        let restored = read_history_from_disk(&path).unwrap();
+        assert_eq!(texts(&restored), vec!["hei"]);
+        assert!(path.exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,636 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::history::{
+    load_history, modify_history, TranslationEntry, TranslationHistory, TranslationRevision,
+    MAX_HISTORY_ENTRIES,
+};
+use crate::translation::TranslationMetadata;
+
+// TMX stores dates in the compact ISO 8601 form, e.g. 20250101T120000Z
+const TMX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryFormat {
+    Jsonl,
+    Csv,
+    Tmx,
+    AnkiTsv, // Source on the front, translation on the back
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub source_language: Option<String>,
+    pub target_language: Option<String>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &TranslationEntry) -> bool {
+        if self.from.is_some_and(|from| entry.timestamp < from) {
+            return false;
+        }
+        if self.to.is_some_and(|to| entry.timestamp > to) {
+            return false;
+        }
+        self.matches_languages(&entry.detected_language, &entry.target_language)
+    }
+
+    /// Whether any part of the UTC day falls inside the date range
+    pub fn matches_day(&self, day: NaiveDate) -> bool {
+        self.from.is_none_or(|from| day >= from.date_naive())
+            && self.to.is_none_or(|to| day <= to.date_naive())
+    }
+
+    pub fn matches_languages(&self, source_language: &str, target_language: &str) -> bool {
+        let language_matches = |filter: &Option<String>, language: &str| {
+            filter
+                .as_ref()
+                .is_none_or(|lang| language.eq_ignore_ascii_case(lang.trim()))
+        };
+        language_matches(&self.source_language, source_language)
+            && language_matches(&self.target_language, target_language)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub total_entries: usize,
+}
+
+// An entry read from an external file; ids and timestamps are kept when the format carries them
+#[derive(Debug, Clone, Deserialize)]
+struct ImportRecord {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    original_text: String,
+    translated_text: String,
+    #[serde(default)]
+    detected_language: Option<String>,
+    #[serde(default)]
+    target_language: Option<String>,
+    #[serde(default)]
+    metadata: Option<TranslationMetadata>,
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default)]
+    revisions: Vec<TranslationRevision>,
+}
+
+impl From<ImportRecord> for TranslationEntry {
+    fn from(record: ImportRecord) -> Self {
+        TranslationEntry {
+            id: record
+                .id
+                .filter(|id| !id.trim().is_empty())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            original_text: record.original_text,
+            translated_text: record.translated_text,
+            detected_language: record
+                .detected_language
+                .filter(|lang| !lang.trim().is_empty())
+                .unwrap_or_else(|| "unknown".to_string()),
+            target_language: record.target_language.unwrap_or_default(),
+            timestamp: record.timestamp.unwrap_or_else(Utc::now),
+            metadata: record.metadata,
+            parent_id: record.parent_id,
+            revisions: record.revisions,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    id: &'a str,
+    timestamp: String,
+    detected_language: &'a str,
+    target_language: &'a str,
+    original_text: &'a str,
+    translated_text: &'a str,
+}
+
+pub async fn export_history(
+    path: &Path,
+    format: HistoryFormat,
+    filter: &HistoryFilter,
+) -> Result<usize> {
+    let history = load_history().await?;
+    let entries: Vec<&TranslationEntry> = history
+        .entries
+        .iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+
+    let contents = match format {
+        HistoryFormat::Jsonl => to_jsonl(&entries)?,
+        HistoryFormat::Csv => to_csv(&entries)?,
+        HistoryFormat::Tmx => to_tmx(&entries),
+        HistoryFormat::AnkiTsv => to_anki_tsv(&entries)?,
+    };
+
+    fs::write(path, contents)?;
+    log::info!(
+        "Exported {} history entries to {}",
+        entries.len(),
+        path.display()
+    );
+    Ok(entries.len())
+}
+
+pub async fn import_history(
+    path: &Path,
+    format: HistoryFormat,
+    filter: &HistoryFilter,
+) -> Result<ImportSummary> {
+    let contents = fs::read_to_string(path)?;
+
+    let records = match format {
+        HistoryFormat::Jsonl => from_jsonl(&contents)?,
+        HistoryFormat::Csv => from_csv(&contents)?,
+        HistoryFormat::Tmx => from_tmx(&contents)?,
+        HistoryFormat::AnkiTsv => from_anki_tsv(&contents)?,
+    };
+
+    let entries: Vec<TranslationEntry> = records
+        .into_iter()
+        .map(TranslationEntry::from)
+        .filter(|entry| filter.matches(entry))
+        .collect();
+
+    let summary = modify_history(move |history| merge_entries(history, entries)).await?;
+
+    log::info!(
+        "Imported {} history entries from {} ({} duplicates skipped)",
+        summary.imported,
+        path.display(),
+        summary.duplicates
+    );
+    Ok(summary)
+}
+
+// Two entries are considered the same translation if both texts match
+fn content_key(entry: &TranslationEntry) -> (String, String) {
+    (
+        entry.original_text.trim().to_string(),
+        entry.translated_text.trim().to_string(),
+    )
+}
+
+// Refuses the whole import when history could not keep every new entry, so nothing is
+// silently truncated
+fn merge_entries(
+    history: &mut TranslationHistory,
+    entries: Vec<TranslationEntry>,
+) -> Result<ImportSummary> {
+    let mut seen_ids: HashSet<String> = history.entries.iter().map(|e| e.id.clone()).collect();
+    let mut seen_content: HashSet<(String, String)> =
+        history.entries.iter().map(content_key).collect();
+
+    let mut summary = ImportSummary::default();
+    let mut new_entries = Vec::new();
+    for entry in entries {
+        let key = content_key(&entry);
+        if seen_ids.contains(&entry.id) || seen_content.contains(&key) {
+            summary.duplicates += 1;
+            continue;
+        }
+        seen_ids.insert(entry.id.clone());
+        seen_content.insert(key);
+        new_entries.push(entry);
+    }
+
+    if history.entries.len() + new_entries.len() > MAX_HISTORY_ENTRIES {
+        return Err(anyhow::anyhow!(
+            "The file has {} new entries and history already holds {}, but it keeps at most {}. Clear history or filter the import first",
+            new_entries.len(),
+            history.entries.len(),
+            MAX_HISTORY_ENTRIES
+        ));
+    }
+
+    // Keep newest entries first, as add_entry does
+    summary.imported = new_entries.len();
+    history.entries.extend(new_entries);
+    history
+        .entries
+        .sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+    summary.total_entries = history.entries.len();
+
+    Ok(summary)
+}
+
+fn to_jsonl(entries: &[&TranslationEntry]) -> Result<String> {
+    let mut out = String::new();
+    for entry in entries {
+        out.push_str(&serde_json::to_string(entry)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn from_jsonl(contents: &str) -> Result<Vec<ImportRecord>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| anyhow::anyhow!("Invalid JSON on line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+fn to_csv(entries: &[&TranslationEntry]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for entry in entries {
+        writer.serialize(CsvRow {
+            id: &entry.id,
+            timestamp: entry.timestamp.to_rfc3339(),
+            detected_language: &entry.detected_language,
+            target_language: &entry.target_language,
+            original_text: &entry.original_text,
+            translated_text: &entry.translated_text,
+        })?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn from_csv(contents: &str) -> Result<Vec<ImportRecord>> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let mut records = Vec::new();
+    for record in reader.deserialize() {
+        records.push(record?);
+    }
+    Ok(records)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// History stores language names, TMX wants BCP 47 codes in xml:lang
+const LANGUAGE_CODES: [(&str, &str); 50] = [
+    ("Arabic", "ar"),
+    ("Bengali", "bn"),
+    ("Bulgarian", "bg"),
+    ("Catalan", "ca"),
+    ("Chinese", "zh"),
+    ("Chinese (Simplified)", "zh-Hans"),
+    ("Chinese (Traditional)", "zh-Hant"),
+    ("Croatian", "hr"),
+    ("Czech", "cs"),
+    ("Danish", "da"),
+    ("Dutch", "nl"),
+    ("English", "en"),
+    ("Estonian", "et"),
+    ("Filipino", "fil"),
+    ("Finnish", "fi"),
+    ("French", "fr"),
+    ("German", "de"),
+    ("Greek", "el"),
+    ("Hebrew", "he"),
+    ("Hindi", "hi"),
+    ("Hungarian", "hu"),
+    ("Icelandic", "is"),
+    ("Indonesian", "id"),
+    ("Irish", "ga"),
+    ("Italian", "it"),
+    ("Japanese", "ja"),
+    ("Korean", "ko"),
+    ("Latvian", "lv"),
+    ("Lithuanian", "lt"),
+    ("Malay", "ms"),
+    ("Norwegian", "no"),
+    ("Norwegian Bokmål", "nb"),
+    ("Norwegian Nynorsk", "nn"),
+    ("Persian", "fa"),
+    ("Polish", "pl"),
+    ("Portuguese", "pt"),
+    ("Romanian", "ro"),
+    ("Russian", "ru"),
+    ("Serbian", "sr"),
+    ("Slovak", "sk"),
+    ("Slovenian", "sl"),
+    ("Spanish", "es"),
+    ("Swahili", "sw"),
+    ("Swedish", "sv"),
+    ("Tamil", "ta"),
+    ("Thai", "th"),
+    ("Turkish", "tr"),
+    ("Ukrainian", "uk"),
+    ("Urdu", "ur"),
+    ("Vietnamese", "vi"),
+];
+
+// Codes like "en" or "pt-BR" are kept, unknown names become "und" (undetermined)
+pub(crate) fn language_code(language: &str) -> String {
+    let language = language.trim();
+    let is_code = !language.is_empty()
+        && language.split('-').enumerate().all(|(i, part)| {
+            (1..=8).contains(&part.len())
+                && part.chars().all(|c| c.is_ascii_alphanumeric())
+                && (i > 0
+                    || (2..=3).contains(&part.len())
+                        && part.chars().all(|c| c.is_ascii_lowercase()))
+        });
+    if is_code {
+        return language.to_string();
+    }
+    let lookup = |name: &str| {
+        LANGUAGE_CODES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, code)| code.to_string())
+    };
+    // "English (US)" falls back to "English"
+    lookup(language)
+        .or_else(|| lookup(language.split(" (").next().unwrap_or(language)))
+        .unwrap_or_else(|| "und".to_string())
+}
+
+fn language_name(code: &str) -> String {
+    LANGUAGE_CODES
+        .iter()
+        .find(|(_, known)| known.eq_ignore_ascii_case(code))
+        .map_or_else(|| code.to_string(), |(name, _)| name.to_string())
+}
+
+fn to_tmx(entries: &[&TranslationEntry]) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<tmx version=\"1.4\">\n");
+    out.push_str(&format!(
+        "  <header creationtool=\"GPTranslate\" creationtoolversion=\"{}\" segtype=\"paragraph\" o-tmf=\"GPTranslate\" adminlang=\"en\" srclang=\"*all*\" datatype=\"plaintext\"/>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    out.push_str("  <body>\n");
+    for entry in entries {
+        out.push_str(&format!(
+            "    <tu tuid=\"{}\" creationdate=\"{}\">\n",
+            escape_xml(&entry.id),
+            entry.timestamp.format(TMX_DATE_FORMAT)
+        ));
+        out.push_str(&format!(
+            "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+            escape_xml(&language_code(&entry.detected_language)),
+            escape_xml(&entry.original_text)
+        ));
+        out.push_str(&format!(
+            "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+            escape_xml(&language_code(&entry.target_language)),
+            escape_xml(&entry.translated_text)
+        ));
+        out.push_str("    </tu>\n");
+    }
+    out.push_str("  </body>\n");
+    out.push_str("</tmx>\n");
+    out
+}
+
+fn from_tmx(contents: &str) -> Result<Vec<ImportRecord>> {
+    let document = roxmltree::Document::parse(contents)
+        .map_err(|e| anyhow::anyhow!("Invalid TMX file: {}", e))?;
+
+    let mut records = Vec::new();
+    for tu in document.descendants().filter(|n| n.has_tag_name("tu")) {
+        // The first variant is the source, the second one the translation
+        let variants: Vec<(Option<String>, String)> = tu
+            .children()
+            .filter(|n| n.has_tag_name("tuv"))
+            .map(|tuv| {
+                let lang = tuv
+                    .attribute((roxmltree::NS_XML_URI, "lang"))
+                    .or_else(|| tuv.attribute("lang"))
+                    .filter(|lang| *lang != "und")
+                    .map(language_name);
+                let text = tuv
+                    .children()
+                    .find(|n| n.has_tag_name("seg"))
+                    .map(|seg| {
+                        seg.descendants()
+                            .filter(|n| n.is_text())
+                            .filter_map(|n| n.text())
+                            .collect::<String>()
+                    })
+                    .unwrap_or_default();
+                (lang, text)
+            })
+            .collect();
+
+        let [(source_lang, original_text), (target_lang, translated_text), ..] =
+            variants.as_slice()
+        else {
+            log::warn!("Skipping TMX translation unit with fewer than two variants");
+            continue;
+        };
+
+        records.push(ImportRecord {
+            id: tu.attribute("tuid").map(|id| id.to_string()),
+            timestamp: tu
+                .attribute("creationdate")
+                .and_then(|date| NaiveDateTime::parse_from_str(date, TMX_DATE_FORMAT).ok())
+                .map(|date| date.and_utc()),
+            original_text: original_text.clone(),
+            translated_text: translated_text.clone(),
+            detected_language: source_lang.clone(),
+            target_language: target_lang.clone(),
+            metadata: None,
+            parent_id: None,
+            revisions: Vec::new(),
+        });
+    }
+    Ok(records)
+}
+
+// Anki tags cannot contain spaces, so languages are stored as e.g. "to::Chinese_(Simplified)"
+fn anki_language_tag(prefix: &str, language: &str) -> String {
+    format!("{}::{}", prefix, language.trim().replace(' ', "_"))
+}
+
+fn to_anki_tsv(entries: &[&TranslationEntry]) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .from_writer(Vec::new());
+    for entry in entries {
+        let tags = format!(
+            "gptranslate {} {}",
+            anki_language_tag("from", &entry.detected_language),
+            anki_language_tag("to", &entry.target_language)
+        );
+        writer.write_record([
+            entry.original_text.as_str(),
+            entry.translated_text.as_str(),
+            tags.as_str(),
+        ])?;
+    }
+
+    let mut out = String::from("#separator:tab\n#html:false\n#columns:Front\tBack\tTags\n");
+    out.push_str(&String::from_utf8(writer.into_inner()?)?);
+    Ok(out)
+}
+
+fn from_anki_tsv(contents: &str) -> Result<Vec<ImportRecord>> {
+    // Skip the "#key:value" header lines Anki puts at the top of the file
+    let mut body = contents;
+    while body.starts_with('#') {
+        body = body.split_once('\n').map_or("", |(_, rest)| rest);
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let (Some(front), Some(back)) = (row.get(0), row.get(1)) else {
+            continue;
+        };
+
+        let tag_language = |prefix: &str| {
+            row.get(2).and_then(|tags| {
+                tags.split_whitespace()
+                    .find_map(|tag| tag.strip_prefix(prefix))
+                    .map(|lang| lang.replace('_', " "))
+            })
+        };
+
+        records.push(ImportRecord {
+            id: None,
+            timestamp: None,
+            original_text: front.to_string(),
+            translated_text: back.to_string(),
+            detected_language: tag_language("from::"),
+            target_language: tag_language("to::"),
+            metadata: None,
+            parent_id: None,
+            revisions: Vec::new(),
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(id: &str, original: &str, translated: &str, minute: u32) -> TranslationEntry {
+        TranslationEntry {
+            id: id.to_string(),
+            original_text: original.to_string(),
+            translated_text: translated.to_string(),
+            detected_language: "Norwegian".to_string(),
+            target_language: "English".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 1, 12, minute, 0).unwrap(),
+            metadata: None,
+            parent_id: None,
+            revisions: Vec::new(),
+        }
+    }
+
+    fn sample() -> Vec<TranslationEntry> {
+        vec![
+            entry("a", "Hei, \"verden\"", "Hello, \"world\"", 1),
+            entry(
+                "b",
+                "To linjer\nog\ttabulator <&>",
+                "Two lines\nand\ttab <&>",
+                2,
+            ),
+        ]
+    }
+
+    // Texts and languages every format keeps
+    fn assert_round_trip(records: Vec<ImportRecord>, keeps_ids: bool) {
+        let expected = sample();
+        assert_eq!(records.len(), expected.len());
+        for (record, expected) in records.into_iter().zip(expected) {
+            let imported = TranslationEntry::from(record);
+            assert_eq!(imported.original_text, expected.original_text);
+            assert_eq!(imported.translated_text, expected.translated_text);
+            assert_eq!(imported.detected_language, expected.detected_language);
+            assert_eq!(imported.target_language, expected.target_language);
+            if keeps_ids {
+                assert_eq!(imported.id, expected.id);
+                assert_eq!(imported.timestamp, expected.timestamp);
+            }
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let entries = sample();
+        let entries: Vec<&TranslationEntry> = entries.iter().collect();
+
+        assert_round_trip(from_jsonl(&to_jsonl(&entries).unwrap()).unwrap(), true);
+        assert_round_trip(from_csv(&to_csv(&entries).unwrap()).unwrap(), true);
+        assert_round_trip(from_tmx(&to_tmx(&entries)).unwrap(), true);
+        assert_round_trip(
+            from_anki_tsv(&to_anki_tsv(&entries).unwrap()).unwrap(),
+            false,
+        );
+    }
+
+    #[test]
+    fn writes_language_codes_to_tmx() {
+        assert_eq!(language_code("Norwegian"), "no");
+        assert_eq!(language_code("chinese (simplified)"), "zh-Hans");
+        assert_eq!(language_code("English (US)"), "en");
+        assert_eq!(language_code("pt-BR"), "pt-BR");
+        assert_eq!(language_code("Klingon"), "und");
+
+        let entries = sample();
+        let tmx = to_tmx(&[&entries[0]]);
+        assert!(tmx.contains("<tuv xml:lang=\"no\">"));
+        assert!(tmx.contains("<tuv xml:lang=\"en\">"));
+    }
+
+    #[test]
+    fn refuses_imports_beyond_the_history_limit() {
+        let mut history = TranslationHistory::new();
+        history.entries = (0..MAX_HISTORY_ENTRIES - 2)
+            .map(|i| {
+                let mut existing = entry(&format!("old{}", i), &i.to_string(), "x", 30);
+                existing.timestamp += chrono::Duration::minutes(i as i64);
+                existing
+            })
+            .collect();
+        let imported = || {
+            vec![
+                entry("new", "ny", "new", 59),
+                entry("old", "gammel", "old", 0),
+                entry("older", "eldre", "older", 0),
+                entry("dup", "0", "x", 0),
+            ]
+        };
+
+        let before = history.entries.len();
+        assert!(merge_entries(&mut history, imported()).is_err());
+        assert_eq!(history.entries.len(), before);
+
+        history.entries.pop();
+        let summary = merge_entries(&mut history, imported()).unwrap();
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.imported, 3);
+        assert_eq!(summary.total_entries, MAX_HISTORY_ENTRIES);
+        assert!(history.entries.iter().any(|entry| entry.id == "new"));
+    }
+}
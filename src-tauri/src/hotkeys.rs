@@ -0,0 +1,639 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+
+lazy_static! {
+    // Shortcuts currently registered with the OS, so bindings can change one at a time
+    static ref REGISTERED: Mutex<BTreeMap<HotkeyAction, Shortcut>> = Mutex::new(BTreeMap::new());
+    static ref LAST_STATUS: Mutex<Vec<BindingStatus>> = Mutex::new(Vec::new());
+}
+
+/// Something a global hotkey can trigger
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    TranslateClipboard,
+    TranslateAlternative, // Translates the clipboard to the alternative target language
+    ToggleWindow,
+    TranslateReplace,
+    OpenHistory,
+    ToggleClipboardWatch,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 6] = [
+        HotkeyAction::TranslateClipboard,
+        HotkeyAction::TranslateAlternative,
+        HotkeyAction::ToggleWindow,
+        HotkeyAction::TranslateReplace,
+        HotkeyAction::OpenHistory,
+        HotkeyAction::ToggleClipboardWatch,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::TranslateClipboard => "Translate clipboard",
+            HotkeyAction::TranslateAlternative => "Translate to alternative language",
+            HotkeyAction::ToggleWindow => "Show or hide window",
+            HotkeyAction::TranslateReplace => "Translate and replace selection",
+            HotkeyAction::OpenHistory => "Open history",
+            HotkeyAction::ToggleClipboardWatch => "Toggle clipboard watching",
+        }
+    }
+}
+
+/// An empty shortcut leaves the action unbound
+pub fn default_hotkeys() -> BTreeMap<HotkeyAction, String> {
+    HotkeyAction::ALL
+        .iter()
+        .map(|action| {
+            let shortcut = match action {
+                HotkeyAction::TranslateClipboard => "CommandOrControl+Alt+C",
+                _ => "",
+            };
+            (*action, shortcut.to_string())
+        })
+        .collect()
+}
+
+// CommandOrControl follows Electron accelerators: Cmd on macOS, Ctrl elsewhere
+const COMMAND_OR_CONTROL: Modifiers = if cfg!(target_os = "macos") {
+    Modifiers::SUPER
+} else {
+    Modifiers::CONTROL
+};
+
+const SUPER_NAME: &str = if cfg!(target_os = "macos") {
+    "Command"
+} else {
+    "Super"
+};
+
+// Names are matched case-insensitively
+const MODIFIERS: &[(&str, Modifiers)] = &[
+    ("ctrl", Modifiers::CONTROL),
+    ("control", Modifiers::CONTROL),
+    ("commandorcontrol", COMMAND_OR_CONTROL),
+    ("cmdorctrl", COMMAND_OR_CONTROL),
+    ("alt", Modifiers::ALT),
+    ("option", Modifiers::ALT),
+    ("shift", Modifiers::SHIFT),
+    ("super", Modifiers::SUPER),
+    ("command", Modifiers::SUPER),
+    ("cmd", Modifiers::SUPER),
+    ("meta", Modifiers::SUPER),
+    ("win", Modifiers::SUPER),
+];
+
+// The first name listed for a key is the one `format_hotkey` writes
+const KEYS: &[(&str, Code)] = &[
+    ("A", Code::KeyA),
+    ("B", Code::KeyB),
+    ("C", Code::KeyC),
+    ("D", Code::KeyD),
+    ("E", Code::KeyE),
+    ("F", Code::KeyF),
+    ("G", Code::KeyG),
+    ("H", Code::KeyH),
+    ("I", Code::KeyI),
+    ("J", Code::KeyJ),
+    ("K", Code::KeyK),
+    ("L", Code::KeyL),
+    ("M", Code::KeyM),
+    ("N", Code::KeyN),
+    ("O", Code::KeyO),
+    ("P", Code::KeyP),
+    ("Q", Code::KeyQ),
+    ("R", Code::KeyR),
+    ("S", Code::KeyS),
+    ("T", Code::KeyT),
+    ("U", Code::KeyU),
+    ("V", Code::KeyV),
+    ("W", Code::KeyW),
+    ("X", Code::KeyX),
+    ("Y", Code::KeyY),
+    ("Z", Code::KeyZ),
+    ("0", Code::Digit0),
+    ("1", Code::Digit1),
+    ("2", Code::Digit2),
+    ("3", Code::Digit3),
+    ("4", Code::Digit4),
+    ("5", Code::Digit5),
+    ("6", Code::Digit6),
+    ("7", Code::Digit7),
+    ("8", Code::Digit8),
+    ("9", Code::Digit9),
+    ("F1", Code::F1),
+    ("F2", Code::F2),
+    ("F3", Code::F3),
+    ("F4", Code::F4),
+    ("F5", Code::F5),
+    ("F6", Code::F6),
+    ("F7", Code::F7),
+    ("F8", Code::F8),
+    ("F9", Code::F9),
+    ("F10", Code::F10),
+    ("F11", Code::F11),
+    ("F12", Code::F12),
+    ("F13", Code::F13),
+    ("F14", Code::F14),
+    ("F15", Code::F15),
+    ("F16", Code::F16),
+    ("F17", Code::F17),
+    ("F18", Code::F18),
+    ("F19", Code::F19),
+    ("F20", Code::F20),
+    ("F21", Code::F21),
+    ("F22", Code::F22),
+    ("F23", Code::F23),
+    ("F24", Code::F24),
+    // Punctuation, by name or by the character on a US layout. "+" separates the parts
+    // of a hotkey and cannot be used as a key, NumpadAdd can
+    ("Minus", Code::Minus),
+    ("-", Code::Minus),
+    ("Equal", Code::Equal),
+    ("=", Code::Equal),
+    ("BracketLeft", Code::BracketLeft),
+    ("[", Code::BracketLeft),
+    ("BracketRight", Code::BracketRight),
+    ("]", Code::BracketRight),
+    ("Backslash", Code::Backslash),
+    ("\\", Code::Backslash),
+    ("Semicolon", Code::Semicolon),
+    (";", Code::Semicolon),
+    ("Quote", Code::Quote),
+    ("'", Code::Quote),
+    ("Comma", Code::Comma),
+    (",", Code::Comma),
+    ("Period", Code::Period),
+    (".", Code::Period),
+    ("Slash", Code::Slash),
+    ("/", Code::Slash),
+    ("Backquote", Code::Backquote),
+    ("`", Code::Backquote),
+    ("Space", Code::Space),
+    ("Tab", Code::Tab),
+    ("Enter", Code::Enter),
+    ("Return", Code::Enter),
+    ("Escape", Code::Escape),
+    ("Esc", Code::Escape),
+    ("Backspace", Code::Backspace),
+    ("Insert", Code::Insert),
+    ("Delete", Code::Delete),
+    ("Del", Code::Delete),
+    ("Home", Code::Home),
+    ("End", Code::End),
+    ("PageUp", Code::PageUp),
+    ("PageDown", Code::PageDown),
+    ("Left", Code::ArrowLeft),
+    ("ArrowLeft", Code::ArrowLeft),
+    ("Right", Code::ArrowRight),
+    ("ArrowRight", Code::ArrowRight),
+    ("Up", Code::ArrowUp),
+    ("ArrowUp", Code::ArrowUp),
+    ("Down", Code::ArrowDown),
+    ("ArrowDown", Code::ArrowDown),
+    ("PrintScreen", Code::PrintScreen),
+    ("ScrollLock", Code::ScrollLock),
+    ("Pause", Code::Pause),
+    ("Numpad0", Code::Numpad0),
+    ("Num0", Code::Numpad0),
+    ("Numpad1", Code::Numpad1),
+    ("Num1", Code::Numpad1),
+    ("Numpad2", Code::Numpad2),
+    ("Num2", Code::Numpad2),
+    ("Numpad3", Code::Numpad3),
+    ("Num3", Code::Numpad3),
+    ("Numpad4", Code::Numpad4),
+    ("Num4", Code::Numpad4),
+    ("Numpad5", Code::Numpad5),
+    ("Num5", Code::Numpad5),
+    ("Numpad6", Code::Numpad6),
+    ("Num6", Code::Numpad6),
+    ("Numpad7", Code::Numpad7),
+    ("Num7", Code::Numpad7),
+    ("Numpad8", Code::Numpad8),
+    ("Num8", Code::Numpad8),
+    ("Numpad9", Code::Numpad9),
+    ("Num9", Code::Numpad9),
+    ("NumpadAdd", Code::NumpadAdd),
+    ("NumAdd", Code::NumpadAdd),
+    ("NumpadSubtract", Code::NumpadSubtract),
+    ("NumSub", Code::NumpadSubtract),
+    ("NumpadMultiply", Code::NumpadMultiply),
+    ("NumMult", Code::NumpadMultiply),
+    ("NumpadDivide", Code::NumpadDivide),
+    ("NumDiv", Code::NumpadDivide),
+    ("NumpadDecimal", Code::NumpadDecimal),
+    ("NumDec", Code::NumpadDecimal),
+    ("NumpadEnter", Code::NumpadEnter),
+    ("NumpadEqual", Code::NumpadEqual),
+    ("MediaPlayPause", Code::MediaPlayPause),
+    ("PlayPause", Code::MediaPlayPause),
+    ("MediaStop", Code::MediaStop),
+    ("MediaTrackNext", Code::MediaTrackNext),
+    ("MediaNextTrack", Code::MediaTrackNext),
+    ("MediaTrackPrevious", Code::MediaTrackPrevious),
+    ("MediaPreviousTrack", Code::MediaTrackPrevious),
+    ("AudioVolumeUp", Code::AudioVolumeUp),
+    ("VolumeUp", Code::AudioVolumeUp),
+    ("AudioVolumeDown", Code::AudioVolumeDown),
+    ("VolumeDown", Code::AudioVolumeDown),
+    ("AudioVolumeMute", Code::AudioVolumeMute),
+    ("VolumeMute", Code::AudioVolumeMute),
+];
+
+/// Why a hotkey string could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotkeyParseError {
+    Empty,
+    EmptyPart, // e.g. "Ctrl++C" or a trailing "+"
+    MissingKey,
+    UnknownKey(String),
+    UnknownModifier(String),
+    DuplicateModifier(String),
+    MisplacedKey(String), // A key before the last part, e.g. "C+Ctrl"
+}
+
+impl std::fmt::Display for HotkeyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HotkeyParseError::Empty => write!(f, "The hotkey is empty"),
+            HotkeyParseError::EmptyPart => write!(
+                f,
+                "The hotkey has an empty part, use NumpadAdd for the + key"
+            ),
+            HotkeyParseError::MissingKey => {
+                write!(f, "The hotkey needs a key after the modifiers")
+            }
+            HotkeyParseError::UnknownKey(key) => write!(f, "\"{}\" is not a known key", key),
+            HotkeyParseError::UnknownModifier(modifier) => write!(
+                f,
+                "\"{}\" is not a modifier, use Ctrl, Alt, Shift, Super or CommandOrControl",
+                modifier
+            ),
+            HotkeyParseError::DuplicateModifier(modifier) => {
+                write!(f, "\"{}\" is used more than once", modifier)
+            }
+            HotkeyParseError::MisplacedKey(key) => {
+                write!(f, "\"{}\" must come last, after the modifiers", key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HotkeyParseError {}
+
+fn find_modifier(name: &str) -> Option<Modifiers> {
+    MODIFIERS
+        .iter()
+        .find(|(modifier, _)| modifier.eq_ignore_ascii_case(name))
+        .map(|(_, modifiers)| *modifiers)
+}
+
+fn find_key(name: &str) -> Option<Code> {
+    KEYS.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, code)| *code)
+}
+
+/// Parses hotkeys such as "CommandOrControl+Alt+C": any number of modifiers joined
+/// by "+", followed by exactly one key
+pub fn parse_hotkey(hotkey: &str) -> Result<Shortcut, HotkeyParseError> {
+    if hotkey.trim().is_empty() {
+        return Err(HotkeyParseError::Empty);
+    }
+    let parts: Vec<&str> = hotkey.split('+').map(|part| part.trim()).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(HotkeyParseError::EmptyPart);
+    }
+
+    let (key, modifier_parts) = parts.split_last().ok_or(HotkeyParseError::Empty)?;
+    let mut modifiers = Modifiers::empty();
+    for part in modifier_parts {
+        let Some(modifier) = find_modifier(part) else {
+            return Err(match find_key(part) {
+                Some(_) => HotkeyParseError::MisplacedKey(part.to_string()),
+                None => HotkeyParseError::UnknownModifier(part.to_string()),
+            });
+        };
+        if modifiers.contains(modifier) {
+            return Err(HotkeyParseError::DuplicateModifier(part.to_string()));
+        }
+        modifiers |= modifier;
+    }
+
+    if find_modifier(key).is_some() {
+        return Err(HotkeyParseError::MissingKey);
+    }
+    let code = find_key(key).ok_or_else(|| HotkeyParseError::UnknownKey(key.to_string()))?;
+    Ok(Shortcut::new(Some(modifiers), code))
+}
+
+/// Writes a shortcut in the canonical form, which `parse_hotkey` reads back unchanged
+pub fn format_hotkey(shortcut: &Shortcut) -> String {
+    let mut parts = Vec::new();
+    for (modifier, name) in [
+        (Modifiers::CONTROL, "Ctrl"),
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::SHIFT, "Shift"),
+        (Modifiers::SUPER, SUPER_NAME),
+    ] {
+        if shortcut.mods.contains(modifier) {
+            parts.push(name.to_string());
+        }
+    }
+    let key = KEYS
+        .iter()
+        .find(|(_, code)| *code == shortcut.key)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| shortcut.key.to_string());
+    parts.push(key);
+    parts.join("+")
+}
+
+/// Registration result of a single binding
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BindingStatus {
+    pub action: HotkeyAction,
+    pub shortcut: String,
+    pub registered: bool,
+    pub error: Option<String>,
+    pub conflict: Option<HotkeyAction>, // Another binding with the same shortcut
+    pub suggestions: Vec<String>,       // Free shortcuts to use instead
+}
+
+// Modifier combinations tried, with the same key, when suggesting alternatives
+const SUGGESTED_MODIFIERS: [Modifiers; 6] = [
+    Modifiers::CONTROL.union(Modifiers::ALT),
+    Modifiers::CONTROL.union(Modifiers::SHIFT),
+    Modifiers::ALT.union(Modifiers::SHIFT),
+    Modifiers::CONTROL
+        .union(Modifiers::ALT)
+        .union(Modifiers::SHIFT),
+    Modifiers::SUPER.union(Modifiers::ALT),
+    Modifiers::SUPER.union(Modifiers::SHIFT),
+];
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Bindings whose shortcut is already used by an earlier binding, mapped to that binding
+pub fn find_duplicates(
+    bindings: &BTreeMap<HotkeyAction, String>,
+) -> BTreeMap<HotkeyAction, HotkeyAction> {
+    let mut seen: HashMap<Shortcut, HotkeyAction> = HashMap::new();
+    let mut duplicates = BTreeMap::new();
+    for action in HotkeyAction::ALL {
+        let Some(Ok(shortcut)) = bindings.get(&action).map(|text| parse_hotkey(text)) else {
+            continue;
+        };
+        match seen.get(&shortcut) {
+            Some(first) => {
+                duplicates.insert(action, *first);
+            }
+            None => {
+                seen.insert(shortcut, action);
+            }
+        }
+    }
+    duplicates
+}
+
+fn suggestion_candidates(shortcut: &Shortcut) -> Vec<Shortcut> {
+    SUGGESTED_MODIFIERS
+        .iter()
+        .filter(|modifiers| **modifiers != shortcut.mods)
+        .map(|modifiers| Shortcut::new(Some(*modifiers), shortcut.key))
+        .collect()
+}
+
+// The OS only tells whether a shortcut is taken by trying to register it
+fn is_free(app: &AppHandle, shortcut: Shortcut) -> bool {
+    let global_shortcut = app.global_shortcut();
+    if global_shortcut.is_registered(shortcut) || global_shortcut.register(shortcut).is_err() {
+        return false;
+    }
+    let _ = global_shortcut.unregister(shortcut);
+    true
+}
+
+fn suggest_alternatives(app: &AppHandle, shortcut: &Shortcut, wanted: &[Shortcut]) -> Vec<String> {
+    suggestion_candidates(shortcut)
+        .into_iter()
+        .filter(|candidate| !wanted.contains(candidate))
+        .filter(|candidate| is_free(app, *candidate))
+        .take(MAX_SUGGESTIONS)
+        .map(|candidate| format_hotkey(&candidate))
+        .collect()
+}
+
+fn register(app: &AppHandle, action: HotkeyAction, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            // Only the key press triggers, not the release
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            log::info!("Global shortcut triggered: {:?}", action);
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                crate::run_hotkey_action(&app, action, None).await;
+            });
+        })
+        .map_err(|e| {
+            format!(
+                "Could not be registered, another application may be using it ({})",
+                e
+            )
+        })
+}
+
+/// Brings the registered shortcuts in line with `bindings`. Each binding is registered
+/// on its own, so one that fails does not affect the others
+pub fn apply_bindings(
+    app: &AppHandle,
+    bindings: &BTreeMap<HotkeyAction, String>,
+) -> Vec<BindingStatus> {
+    let mut registered = REGISTERED.lock().unwrap();
+    let duplicates = find_duplicates(bindings);
+
+    let wanted: Vec<(HotkeyAction, String, Result<Option<Shortcut>, String>)> = HotkeyAction::ALL
+        .into_iter()
+        .map(|action| {
+            let text = bindings
+                .get(&action)
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default();
+            let shortcut = if text.is_empty() {
+                Ok(None)
+            } else {
+                parse_hotkey(&text).map(Some).map_err(|e| e.to_string())
+            };
+            (action, text, shortcut)
+        })
+        .collect();
+    let wanted_shortcuts: Vec<Shortcut> = wanted
+        .iter()
+        .filter_map(|(_, _, shortcut)| shortcut.clone().ok().flatten())
+        .collect();
+
+    // Release changed bindings first, so a shortcut can move from one action to another
+    for (action, _, shortcut) in &wanted {
+        let unchanged = matches!(
+            (shortcut, registered.get(action)),
+            (Ok(Some(shortcut)), Some(current)) if shortcut == current
+        ) && !duplicates.contains_key(action);
+        if unchanged {
+            continue;
+        }
+        let unregistered = registered
+            .remove(action)
+            .map(|previous| app.global_shortcut().unregister(previous));
+        if let Some(Err(e)) = unregistered {
+            log::warn!("Failed to unregister shortcut for {:?}: {}", action, e);
+        }
+    }
+
+    let mut statuses = Vec::new();
+    for (action, text, shortcut) in wanted {
+        let mut status = BindingStatus {
+            action,
+            shortcut: text,
+            registered: false,
+            error: None,
+            conflict: duplicates.get(&action).copied(),
+            suggestions: Vec::new(),
+        };
+
+        match (shortcut, status.conflict) {
+            (Ok(Some(shortcut)), _) if registered.get(&action) == Some(&shortcut) => {
+                status.registered = true;
+            }
+            (Ok(Some(shortcut)), Some(conflict)) => {
+                status.error = Some(format!("Already used for {}", conflict.label()));
+                status.suggestions = suggest_alternatives(app, &shortcut, &wanted_shortcuts);
+            }
+            (Ok(Some(shortcut)), None) => match register(app, action, shortcut) {
+                Ok(()) => {
+                    registered.insert(action, shortcut);
+                    status.registered = true;
+                    log::info!(
+                        "Global shortcut registered: {} for {:?}",
+                        status.shortcut,
+                        action
+                    );
+                }
+                Err(error) => {
+                    status.error = Some(error);
+                    status.suggestions = suggest_alternatives(app, &shortcut, &wanted_shortcuts);
+                }
+            },
+            (Ok(None), _) => {}
+            (Err(error), _) => status.error = Some(error),
+        }
+
+        if let Some(error) = &status.error {
+            log::error!(
+                "Failed to register {} for {:?}: {}",
+                status.shortcut,
+                action,
+                error
+            );
+        }
+        statuses.push(status);
+    }
+
+    *LAST_STATUS.lock().unwrap() = statuses.clone();
+    statuses
+}
+
+pub fn last_status() -> Vec<BindingStatus> {
+    LAST_STATUS.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_and_keys() {
+        let shortcut = parse_hotkey("ctrl + Shift+alt+F13").unwrap();
+        assert_eq!(
+            shortcut.mods,
+            Modifiers::CONTROL | Modifiers::SHIFT | Modifiers::ALT
+        );
+        assert_eq!(shortcut.key, Code::F13);
+
+        assert_eq!(parse_hotkey("Alt+/").unwrap().key, Code::Slash);
+        assert_eq!(parse_hotkey("Alt+Slash").unwrap().key, Code::Slash);
+        assert_eq!(parse_hotkey("Ctrl+Num5").unwrap().key, Code::Numpad5);
+        assert_eq!(parse_hotkey("VolumeUp").unwrap().key, Code::AudioVolumeUp);
+        assert_eq!(
+            parse_hotkey("CommandOrControl+C").unwrap().mods,
+            COMMAND_OR_CONTROL
+        );
+    }
+
+    #[test]
+    fn reports_why_a_hotkey_is_invalid() {
+        assert_eq!(parse_hotkey("  "), Err(HotkeyParseError::Empty));
+        assert_eq!(parse_hotkey("Ctrl++"), Err(HotkeyParseError::EmptyPart));
+        assert_eq!(parse_hotkey("Ctrl+Alt"), Err(HotkeyParseError::MissingKey));
+        assert_eq!(
+            parse_hotkey("Ctrl+F25"),
+            Err(HotkeyParseError::UnknownKey("F25".to_string()))
+        );
+        assert_eq!(
+            parse_hotkey("Hyper+C"),
+            Err(HotkeyParseError::UnknownModifier("Hyper".to_string()))
+        );
+        assert_eq!(
+            parse_hotkey("Ctrl+Control+C"),
+            Err(HotkeyParseError::DuplicateModifier("Control".to_string()))
+        );
+        assert_eq!(
+            parse_hotkey("C+Ctrl+X"),
+            Err(HotkeyParseError::MisplacedKey("C".to_string()))
+        );
+    }
+
+    #[test]
+    fn finds_bindings_that_share_a_shortcut() {
+        let mut bindings = default_hotkeys();
+        bindings.insert(HotkeyAction::ToggleWindow, "ctrl+shift+h".to_string());
+        bindings.insert(HotkeyAction::OpenHistory, "Shift+Control+H".to_string());
+        bindings.insert(HotkeyAction::TranslateReplace, "Ctrl+Shift+R".to_string());
+
+        let duplicates = find_duplicates(&bindings);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(
+            duplicates.get(&HotkeyAction::OpenHistory),
+            Some(&HotkeyAction::ToggleWindow)
+        );
+    }
+
+    #[test]
+    fn formatting_round_trips() {
+        for hotkey in [
+            "Ctrl+Alt+C",
+            "shift+super+-",
+            "Alt+Numpad0",
+            "MediaPlayPause",
+            "Ctrl+Shift+F24",
+            "Alt+`",
+            "Ctrl+PageDown",
+        ] {
+            let shortcut = parse_hotkey(hotkey).unwrap();
+            let formatted = format_hotkey(&shortcut);
+            assert_eq!(parse_hotkey(&formatted).unwrap(), shortcut, "{}", hotkey);
+            assert_eq!(format_hotkey(&parse_hotkey(&formatted).unwrap()), formatted);
+        }
+        assert_eq!(
+            format_hotkey(&parse_hotkey("shift+alt+ctrl+[").unwrap()),
+            "Ctrl+Alt+Shift+BracketLeft"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use std::time::Duration;
+
+// The hotkey's modifiers are usually still held when an action starts, and would turn
+// a synthetic Ctrl+C into something else
+const MODIFIER_RELEASE_TIMEOUT: Duration = Duration::from_millis(1000);
+const MODIFIER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sends the copy and paste shortcuts to the focused application
+pub trait KeyboardInput {
+    fn copy_selection(&self) -> Result<()>;
+    fn paste(&self) -> Result<()>;
+}
+
+impl<T: KeyboardInput> KeyboardInput for std::sync::Arc<T> {
+    fn copy_selection(&self) -> Result<()> {
+        (**self).copy_selection()
+    }
+
+    fn paste(&self) -> Result<()> {
+        (**self).paste()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ClipboardShortcut {
+    Copy,
+    Paste,
+}
+
+/// Synthetic key events: XTEST on Linux (X11 only), SendInput on Windows and
+/// System Events on macOS
+#[derive(Clone, Copy)]
+pub struct SystemKeyboard;
+
+impl KeyboardInput for SystemKeyboard {
+    fn copy_selection(&self) -> Result<()> {
+        platform::send(ClipboardShortcut::Copy)
+    }
+
+    fn paste(&self) -> Result<()> {
+        platform::send(ClipboardShortcut::Paste)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{ClipboardShortcut, MODIFIER_POLL_INTERVAL, MODIFIER_RELEASE_TIMEOUT};
+    use anyhow::{Context, Result};
+    use std::time::Instant;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{
+        ConnectionExt as _, KeyButMask, Keycode, Window, KEY_PRESS_EVENT, KEY_RELEASE_EVENT,
+    };
+    use x11rb::protocol::xtest::ConnectionExt as _;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    const XK_CONTROL_L: u32 = 0xffe3;
+    const XK_C: u32 = 0x0063;
+    const XK_V: u32 = 0x0076;
+
+    fn keycode(conn: &impl Connection, keysym: u32) -> Result<Keycode> {
+        let setup = conn.setup();
+        let count = setup.max_keycode - setup.min_keycode + 1;
+        let mapping = conn
+            .get_keyboard_mapping(setup.min_keycode, count)?
+            .reply()?;
+        let per_keycode = usize::from(mapping.keysyms_per_keycode).max(1);
+        mapping
+            .keysyms
+            .chunks(per_keycode)
+            .position(|keysyms| keysyms.contains(&keysym))
+            .map(|index| setup.min_keycode + index as u8)
+            .ok_or_else(|| anyhow::anyhow!("No key on the keyboard produces {:#x}", keysym))
+    }
+
+    fn wait_for_modifier_release(conn: &impl Connection, root: Window) -> Result<()> {
+        let modifiers = u16::from(
+            KeyButMask::SHIFT | KeyButMask::CONTROL | KeyButMask::MOD1 | KeyButMask::MOD4,
+        );
+        let deadline = Instant::now() + MODIFIER_RELEASE_TIMEOUT;
+        while u16::from(conn.query_pointer(root)?.reply()?.mask) & modifiers != 0 {
+            if Instant::now() >= deadline {
+                log::warn!("Modifier keys are still held, sending the key events anyway");
+                break;
+            }
+            std::thread::sleep(MODIFIER_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    pub fn send(shortcut: ClipboardShortcut) -> Result<()> {
+        let (conn, screen) =
+            x11rb::connect(None).context("Typing into other applications needs an X11 session")?;
+        let root = conn.setup().roots[screen].root;
+        wait_for_modifier_release(&conn, root)?;
+
+        let control = keycode(&conn, XK_CONTROL_L)?;
+        let key = keycode(
+            &conn,
+            match shortcut {
+                ClipboardShortcut::Copy => XK_C,
+                ClipboardShortcut::Paste => XK_V,
+            },
+        )?;
+        for (event, code) in [
+            (KEY_PRESS_EVENT, control),
+            (KEY_PRESS_EVENT, key),
+            (KEY_RELEASE_EVENT, key),
+            (KEY_RELEASE_EVENT, control),
+        ] {
+            conn.xtest_fake_input(event, code, x11rb::CURRENT_TIME, root, 0, 0, 0)?;
+        }
+        conn.sync()?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{ClipboardShortcut, MODIFIER_POLL_INTERVAL, MODIFIER_RELEASE_TIMEOUT};
+    use anyhow::Result;
+    use std::time::Instant;
+    use winapi::um::winuser::{
+        GetAsyncKeyState, SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP,
+        VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN, VK_SHIFT,
+    };
+
+    fn wait_for_modifier_release() {
+        let deadline = Instant::now() + MODIFIER_RELEASE_TIMEOUT;
+        let held = || {
+            [VK_CONTROL, VK_MENU, VK_SHIFT, VK_LWIN, VK_RWIN]
+                .iter()
+                .any(|key| unsafe { GetAsyncKeyState(*key) } as u16 & 0x8000 != 0)
+        };
+        while held() && Instant::now() < deadline {
+            std::thread::sleep(MODIFIER_POLL_INTERVAL);
+        }
+    }
+
+    fn key_input(key: i32, flags: u32) -> INPUT {
+        let mut input: INPUT = unsafe { std::mem::zeroed() };
+        input.type_ = INPUT_KEYBOARD;
+        unsafe {
+            *input.u.ki_mut() = KEYBDINPUT {
+                wVk: key as u16,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            };
+        }
+        input
+    }
+
+    pub fn send(shortcut: ClipboardShortcut) -> Result<()> {
+        wait_for_modifier_release();
+        let key = match shortcut {
+            ClipboardShortcut::Copy => 'C' as i32,
+            ClipboardShortcut::Paste => 'V' as i32,
+        };
+        let mut inputs = [
+            key_input(VK_CONTROL, 0),
+            key_input(key, 0),
+            key_input(key, KEYEVENTF_KEYUP),
+            key_input(VK_CONTROL, KEYEVENTF_KEYUP),
+        ];
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_mut_ptr(),
+                std::mem::size_of::<INPUT>() as i32,
+            )
+        };
+        if sent != inputs.len() as u32 {
+            return Err(anyhow::anyhow!("Windows blocked the synthetic key events"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::ClipboardShortcut;
+    use anyhow::Result;
+    use std::process::Command;
+
+    pub fn send(shortcut: ClipboardShortcut) -> Result<()> {
+        let key = match shortcut {
+            ClipboardShortcut::Copy => "c",
+            ClipboardShortcut::Paste => "v",
+        };
+        let script = format!(
+            "tell application \"System Events\" to keystroke \"{}\" using command down",
+            key
+        );
+        let status = Command::new("osascript").args(["-e", &script]).status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "System Events refused the key press, allow GPTranslate under Accessibility"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::ClipboardShortcut;
+    use anyhow::Result;
+
+    pub fn send(_shortcut: ClipboardShortcut) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Typing into other applications is not supported on this platform"
+        ))
+    }
+}
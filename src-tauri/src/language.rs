@@ -0,0 +1,256 @@
+use crate::history_io::language_code;
+
+// Texts with fewer words are too short to tell languages apart by their stopwords
+const MIN_WORDS: usize = 4;
+// Share of the words that must be stopwords of the target language
+const MIN_STOPWORD_SHARE: f64 = 0.2;
+// Share of the letters that must be in the target language's script
+const MIN_SCRIPT_SHARE: f64 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Han,
+    Kana,
+    Hangul,
+    Thai,
+    Devanagari,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c {
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' => Script::Greek,
+        '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0600}'..='\u{06FF}' => Script::Arabic,
+        '\u{0900}'..='\u{097F}' => Script::Devanagari,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{3040}'..='\u{30FF}' => Script::Kana,
+        '\u{4E00}'..='\u{9FFF}' => Script::Han,
+        '\u{AC00}'..='\u{D7AF}' => Script::Hangul,
+        _ => Script::Other,
+    }
+}
+
+// Languages that are the only one in the list written in their script
+const SCRIPT_LANGUAGES: [(&str, Script); 5] = [
+    ("el", Script::Greek),
+    ("he", Script::Hebrew),
+    ("ko", Script::Hangul),
+    ("th", Script::Thai),
+    ("ja", Script::Kana),
+];
+
+// Frequent short words, picked so that close languages differ in a few of them
+const STOPWORDS: [(&str, &[&str]); 10] = [
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "of", "to", "in", "it", "that", "this", "with", "for",
+            "not", "you", "was", "be", "have", "on", "what", "how",
+        ],
+    ),
+    (
+        "no",
+        &[
+            "og", "er", "ikke", "det", "jeg", "på", "som", "med", "har", "til", "av", "å", "hva",
+            "vi", "kan", "skal", "ble", "hvordan", "meg", "dette",
+        ],
+    ),
+    (
+        "da",
+        &[
+            "og", "er", "ikke", "det", "jeg", "på", "som", "med", "har", "til", "af", "at", "hvad",
+            "vi", "kan", "skal", "blev", "hvordan", "mig", "dette",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "är", "inte", "det", "jag", "på", "som", "med", "har", "till", "av", "att",
+            "vad", "vi", "kan", "ska", "blev", "hur", "mig", "detta",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ich", "mit", "zu", "ein", "eine", "auf",
+            "für", "von", "sie", "es", "wie", "was", "wir", "sind",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "niet", "ik", "met", "van", "op", "voor", "dat",
+            "zijn", "wat", "hoe", "we", "ze", "naar", "er", "maar",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "pas", "je", "de", "des", "un", "une", "que", "qui",
+            "pour", "dans", "ce", "il", "nous", "vous", "avec",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "es", "no", "de", "que", "en", "un", "una", "por",
+            "para", "con", "se", "lo", "yo", "como", "está",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "la", "e", "è", "non", "di", "che", "un", "una", "per", "con", "sono", "io",
+            "gli", "del", "della", "come", "questo", "ho", "mi",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "a", "os", "as", "e", "é", "não", "de", "que", "um", "uma", "para", "com", "em",
+            "do", "da", "eu", "como", "isso", "você",
+        ],
+    ),
+];
+
+// Bokmål and Nynorsk are compared as Norwegian
+fn base_code(language: &str) -> Option<String> {
+    let code = language_code(language);
+    let base = code.split('-').next().unwrap_or(&code).to_lowercase();
+    match base.as_str() {
+        "und" => None,
+        "nb" | "nn" => Some("no".to_string()),
+        _ => Some(base),
+    }
+}
+
+/// Compares language names or codes the model and the settings use, so "English (US)",
+/// "english" and "en-GB" are all English
+pub fn same_language(a: &str, b: &str) -> bool {
+    match (base_code(a), base_code(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => {
+            let name = |language: &str| {
+                let language = language.trim();
+                language
+                    .split(" (")
+                    .next()
+                    .unwrap_or(language)
+                    .trim()
+                    .to_lowercase()
+            };
+            name(a) == name(b)
+        }
+    }
+}
+
+/// Cheap local guess whether the text is already written in `language`. Only answers
+/// yes when the script or the stopwords make it clear, anything else is left to the model
+pub fn is_probably_in(text: &str, language: &str) -> bool {
+    let Some(code) = base_code(language) else {
+        return false;
+    };
+
+    let letters: Vec<Script> = text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .map(script)
+        .collect();
+    if letters.is_empty() {
+        return false;
+    }
+    let share = |wanted: &[Script]| {
+        letters.iter().filter(|s| wanted.contains(s)).count() as f64 / letters.len() as f64
+    };
+
+    if let Some((_, own)) = SCRIPT_LANGUAGES.iter().find(|(known, _)| *known == code) {
+        // Japanese mixes kana with Han characters, which alone would be Chinese
+        return if *own == Script::Kana {
+            letters.contains(&Script::Kana)
+                && share(&[Script::Kana, Script::Han]) >= MIN_SCRIPT_SHARE
+        } else {
+            share(&[*own]) >= MIN_SCRIPT_SHARE
+        };
+    }
+
+    let Some((_, own_words)) = STOPWORDS.iter().find(|(known, _)| *known == code) else {
+        return false;
+    };
+    if share(&[Script::Latin]) < MIN_SCRIPT_SHARE {
+        return false;
+    }
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return false;
+    }
+    let stopword_share = |list: &[&str]| {
+        words
+            .iter()
+            .filter(|word| list.contains(&word.as_str()))
+            .count() as f64
+            / words.len() as f64
+    };
+
+    let own = stopword_share(own_words);
+    let best_other = STOPWORDS
+        .iter()
+        .filter(|(known, _)| *known != code)
+        .map(|(_, list)| stopword_share(list))
+        .fold(0.0, f64::max);
+    own >= MIN_STOPWORD_SHARE && own > best_other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_language_names_and_codes() {
+        assert!(same_language("English (US)", "English"));
+        assert!(same_language("english", "en-GB"));
+        assert!(same_language("Norwegian Bokmål", "Norwegian"));
+        assert!(same_language("Klingon", "klingon"));
+        assert!(!same_language("Danish", "Norwegian"));
+        assert!(!same_language("Chinese", "Japanese"));
+    }
+
+    #[test]
+    fn recognises_text_already_in_the_target_language() {
+        assert!(is_probably_in(
+            "This is the text that I want to keep.",
+            "English"
+        ));
+        assert!(is_probably_in(
+            "Jeg vet ikke hva det er, men det er fint.",
+            "Norwegian"
+        ));
+        assert!(is_probably_in("これは日本語の文章です", "Japanese"));
+        assert!(is_probably_in("이것은 한국어 문장입니다", "Korean"));
+
+        // Danish and Norwegian share most stopwords, only the differing ones decide
+        assert!(!is_probably_in(
+            "Jeg ved ikke hvad det er, men det er fint.",
+            "Norwegian"
+        ));
+        assert!(!is_probably_in(
+            "Das ist nicht der Text, den ich meine.",
+            "English"
+        ));
+        assert!(!is_probably_in("这是中文句子", "Japanese"));
+        assert!(!is_probably_in("Hello", "English"));
+        // Passwords and codes have no stopwords and are left to the model
+        assert!(!is_probably_in("hunter2 Xq9!zR7#", "English"));
+    }
+}
@@ -1,29 +1,64 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_autostart::ManagerExt;
 use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
 use tokio::sync::Mutex;
 
 #[cfg(target_os = "windows")]
 use winapi::um::winuser::{GetSysColor, COLOR_WINDOW};
 
+mod chords;
+mod clipboard;
+mod clipboard_watcher;
 mod config;
+mod config_layers;
+mod config_watcher;
 mod history;
+mod history_io;
+mod hotkeys;
+mod input;
+mod language;
+mod ocr;
+mod paths;
+mod profiles;
+mod replace;
+mod rich_text;
+mod settings_bundle;
+mod spending;
+mod stats;
 pub mod theme;
 mod translation;
 mod tray;
+mod vault;
 
-use config::Config;
+use clipboard::ClipboardBackend;
+use config::{Config, ConfigProblem};
+use config_layers::ConfigLayer;
 use history::{
-    add_translation_to_history, clear_translation_history, get_translation_history,
-    TranslationHistory,
+    add_translation_to_history, clear_translation_history, get_history_entry,
+    get_translation_history, update_translation_in_history, TranslationEntry, TranslationHistory,
 };
-use translation::{TranslationResult, TranslationService};
+use history_io::{HistoryFilter, HistoryFormat, ImportSummary};
+use hotkeys::{BindingStatus, HotkeyAction};
+use paths::StoragePaths;
+use profiles::ProfileSummary;
+use settings_bundle::SettingsImportPreview;
+use spending::{CostEstimate, SpendingSummary};
+use stats::UsageStats;
+use translation::{
+    CaptureSource, FailureKind, TranslationFailure, TranslationOverrides, TranslationResult,
+    TranslationService,
+};
+use vault::EncryptionStatus;
 
 // Application state
 pub struct AppState {
     pub config: Arc<Mutex<Config>>,
     pub translation_service: Arc<Mutex<TranslationService>>,
+    pub config_error: Arc<Mutex<Option<String>>>, // Set when config.json could not be loaded at startup
 }
 
 #[cfg(target_os = "windows")]
@@ -79,60 +114,147 @@ async fn get_clipboard_text(app: AppHandle) -> Result<String, String> {
 // Now using only the translate function which has better duplicate detection
 
 #[tauri::command]
-async fn translate(text: String, config: State<'_, AppState>) -> Result<TranslationResult, String> {
-    match translation::translate_text(text, config).await {
+async fn translate(
+    text: String,
+    source: Option<CaptureSource>,
+    overrides: Option<TranslationOverrides>,
+    config: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TranslationResult, TranslationFailure> {
+    let source = source.unwrap_or_default();
+    let result = match overrides {
+        Some(overrides) => {
+            let mut config = config.config.lock().await.clone();
+            overrides.apply(&mut config);
+            translation::translate_with_config(text, source, config).await
+        }
+        None => translation::translate_text(text, source, config).await,
+    };
+    match result {
         Ok(response) => {
             // Add to history
-            if let Err(e) = add_translation_to_history(
-                response.original_text.clone(),
-                response.translated_text.clone(),
-                response.detected_language.clone(),
-                response.target_language.clone(),
-            ) {
+            if let Err(e) = add_translation_to_history(&response, None).await {
                 log::error!("Failed to add translation to history: {}", e);
             }
 
+            notify_budget_warning(&app, response.budget_warning.as_deref());
+
             // Convert TranslationResponse to TranslationResult for return
             Ok(TranslationResult {
                 detected_language: response.detected_language,
                 translated_text: response.translated_text,
+                metadata: response.metadata,
+                budget_warning: response.budget_warning,
+                source_text: None,
             })
         }
         Err(translation::Error::DuplicateRequest) => {
             // For duplicate requests, we'll just return an empty response
             // The UI will handle this appropriately
             log::info!("Skipping duplicate translation request");
-            Err("Duplicate request".to_string())
+            Err(translation::Error::DuplicateRequest.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn notify_budget_warning(app: &AppHandle, warning: Option<&str>) {
+    if let Some(message) = warning {
+        log::warn!("{}", message);
+        if let Err(e) = app
+            .notification()
+            .builder()
+            .title("GPTranslate spending limit")
+            .body(message)
+            .show()
+        {
+            log::error!("Failed to show spending notification: {}", e);
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
+#[tauri::command]
+async fn estimate_translation_cost(
+    text: String,
+    state: State<'_, AppState>,
+) -> Result<CostEstimate, String> {
+    let service = state.translation_service.lock().await;
+    service
+        .estimate_cost(&text)
+        .map_err(|e| format!("Failed to estimate translation cost: {}", e))
+}
+
+#[tauri::command]
+async fn get_spending_summary(state: State<'_, AppState>) -> Result<SpendingSummary, String> {
+    let limits = state.config.lock().await.budget.clone();
+    spending::get_spending_summary(&limits)
+        .map_err(|e| format!("Failed to read spending data: {}", e))
+}
+
 #[tauri::command]
 async fn get_config(state: State<'_, AppState>) -> Result<Config, String> {
     let config = state.config.lock().await;
     Ok(config.clone())
 }
 
+/// Validates the given settings, or the active config when none are passed
+#[tauri::command]
+async fn validate_config(
+    config: Option<Config>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ConfigProblem>, String> {
+    let config = match config {
+        Some(config) => config,
+        None => state.config.lock().await.clone(),
+    };
+    Ok(config.validate())
+}
+
+/// Reports which layer (default, file, policy, env or cli) each config value came from
+#[tauri::command]
+async fn get_config_sources() -> Result<BTreeMap<String, ConfigLayer>, String> {
+    Ok(config_layers::sources())
+}
+
+/// Where config and data files are kept, for display in settings
+#[tauri::command]
+async fn get_storage_paths() -> Result<StoragePaths, String> {
+    paths::storage_paths().map_err(|e| format!("Failed to resolve storage paths: {}", e))
+}
+
+#[tauri::command]
+async fn get_config_error(state: State<'_, AppState>) -> Result<Option<String>, String> {
+    Ok(state.config_error.lock().await.clone())
+}
+
 #[tauri::command]
 async fn save_config(
     new_config: Config,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
+    // Fields set by the policy file, environment or command line keep their override
+    let new_config = config_layers::reapply(&new_config).map_err(|e| e.to_string())?;
+
     // Check if hotkey changed
     let old_config = {
         let config = state.config.lock().await;
         config.clone()
     };
 
-    let hotkey_changed = old_config.hotkey != new_config.hotkey;
+    let hotkey_changed = old_config.hotkeys != new_config.hotkeys
+        || old_config.hotkey_chords != new_config.hotkey_chords;
 
     match new_config.save() {
         Ok(_) => {
             // Update the config in the state
             let mut config = state.config.lock().await;
             *config = new_config.clone();
+            *state.config_error.lock().await = None;
+
+            if let Err(e) = profiles::update_active_profile(&new_config) {
+                log::error!("Failed to update the active profile: {}", e);
+            }
 
             // Update translation service with new config
             let mut service = state.translation_service.lock().await;
@@ -144,6 +266,12 @@ async fn save_config(
                     log::error!("Failed to update global shortcut: {}", e);
                 }
             }
+            if old_config.auto_start != new_config.auto_start {
+                set_autostart(&app, new_config.auto_start);
+            }
+            if old_config.clipboard_watch.enabled != new_config.clipboard_watch.enabled {
+                refresh_tray_menu(&app, &new_config);
+            }
 
             Ok(())
         }
@@ -151,8 +279,22 @@ async fn save_config(
     }
 }
 
+/// Returns the canonical form of a valid hotkey, or why it cannot be used
+#[tauri::command]
+async fn validate_hotkey(hotkey: String) -> Result<String, String> {
+    hotkeys::parse_hotkey(&hotkey)
+        .map(|shortcut| hotkeys::format_hotkey(&shortcut))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_hotkey_status() -> Result<Vec<BindingStatus>, String> {
+    Ok(hotkeys::last_status())
+}
+
 #[tauri::command]
 async fn copy_to_clipboard(text: String, app: AppHandle) -> Result<(), String> {
+    clipboard_watcher::note_own_write(&text);
     app.clipboard()
         .write_text(text)
         .map_err(|e| format!("Failed to copy to clipboard: {}", e))
@@ -172,6 +314,7 @@ async fn test_translation_from_clipboard(
     if text.trim().is_empty() {
         return Err("Clipboard is empty".to_string());
     }
+    translation::check_unlocked().map_err(|e| e.to_string())?;
 
     // Translate the text
     let service = state.translation_service.lock().await;
@@ -182,6 +325,7 @@ async fn test_translation_from_clipboard(
                 result.detected_language,
                 result.translated_text
             );
+            notify_budget_warning(&app, result.budget_warning.as_deref());
             Ok(result)
         }
         Err(e) => {
@@ -191,6 +335,87 @@ async fn test_translation_from_clipboard(
     }
 }
 
+fn clipboard_failure(message: impl Into<String>) -> TranslationFailure {
+    TranslationFailure::new(FailureKind::Clipboard, message)
+}
+
+#[tauri::command]
+async fn translate_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<TranslationResult, TranslationFailure> {
+    let png = clipboard::AppClipboard::new(&app)
+        .read_image()
+        .map_err(|e| clipboard_failure(format!("Failed to read clipboard image: {}", e)))?
+        .ok_or_else(|| clipboard_failure("The clipboard holds no image"))?
+        .fit_within(translation::MAX_IMAGE_SIDE)
+        .and_then(|image| image.to_png())
+        .map_err(|e| clipboard_failure(format!("Failed to encode clipboard image: {}", e)))?;
+    let config = state.config.lock().await.clone();
+
+    let response = translation::translate_image_with_config(&png, config).await?;
+    if let Err(e) = add_translation_to_history(&response, None).await {
+        log::error!("Failed to add translation to history: {}", e);
+    }
+    notify_budget_warning(&app, response.budget_warning.as_deref());
+
+    Ok(TranslationResult {
+        detected_language: response.detected_language,
+        translated_text: response.translated_text,
+        metadata: response.metadata,
+        budget_warning: response.budget_warning,
+        source_text: Some(response.original_text),
+    })
+}
+
+#[derive(Serialize)]
+struct ImageTranslation {
+    ocr: ocr::OcrResult, // Paragraph boxes carry their own translations
+    detected_language: String,
+    translated_text: String,
+}
+
+#[tauri::command]
+async fn translate_clipboard_image(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ImageTranslation, TranslationFailure> {
+    let image = clipboard::AppClipboard::new(&app)
+        .read_image()
+        .map_err(|e| clipboard_failure(format!("Failed to read clipboard image: {}", e)))?
+        .ok_or_else(|| clipboard_failure("The clipboard holds no image"))?;
+    let config = state.config.lock().await.clone();
+
+    let mut result = ocr::recognize(&image, &config.ocr).await.map_err(|e| {
+        TranslationFailure::new(
+            FailureKind::Ocr,
+            format!("Failed to read text from the image: {}", e),
+        )
+    })?;
+    if result.text.trim().is_empty() {
+        return Err(TranslationFailure::new(
+            FailureKind::Ocr,
+            "No text found in the image",
+        ));
+    }
+
+    let request = ocr::translation_request(&result);
+    let mut response =
+        translation::translate_with_config(request, CaptureSource::Image, config).await?;
+    response.translated_text = ocr::apply_translation(&mut result, &response.translated_text);
+    response.original_text = result.text.clone();
+    if let Err(e) = add_translation_to_history(&response, None).await {
+        log::error!("Failed to add translation to history: {}", e);
+    }
+    notify_budget_warning(&app, response.budget_warning.as_deref());
+
+    Ok(ImageTranslation {
+        ocr: result,
+        detected_language: response.detected_language,
+        translated_text: response.translated_text,
+    })
+}
+
 fn extract_api_version_from_url(url: &str) -> Option<String> {
     // Try to extract api-version from URL query parameters
     if let Ok(parsed_url) = url::Url::parse(url) {
@@ -267,174 +492,602 @@ async fn validate_api_key(
 
 #[tauri::command]
 async fn get_translation_history_cmd() -> Result<TranslationHistory, String> {
-    get_translation_history().map_err(|e| format!("Failed to get translation history: {}", e))
+    get_translation_history()
+        .await
+        .map_err(|e| format!("Failed to get translation history: {}", e))
 }
 
 #[tauri::command]
 async fn clear_translation_history_cmd() -> Result<(), String> {
-    clear_translation_history().map_err(|e| format!("Failed to clear translation history: {}", e))
+    clear_translation_history()
+        .await
+        .map_err(|e| format!("Failed to clear translation history: {}", e))
 }
 
 #[tauri::command]
-async fn reset_detected_language() -> Result<(), String> {
-    log::info!("Detected language reset requested");
+async fn retranslate_history_entry(
+    id: String,
+    overrides: Option<TranslationOverrides>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<TranslationEntry, String> {
+    let entry = get_history_entry(&id).await.map_err(|e| e.to_string())?;
+
+    let mut config = state.config.lock().await.clone();
+    overrides.unwrap_or_default().apply(&mut config);
+
+    let response =
+        translation::translate_with_config(entry.original_text, CaptureSource::History, config)
+            .await
+            .map_err(|e| e.to_string())?;
+    notify_budget_warning(&app, response.budget_warning.as_deref());
+
+    add_translation_to_history(&response, Some(entry.id))
+        .await
+        .map_err(|e| format!("Failed to add translation to history: {}", e))
+}
+
+#[tauri::command]
+async fn update_history_entry(
+    id: String,
+    translated_text: String,
+) -> Result<TranslationEntry, String> {
+    update_translation_in_history(&id, translated_text)
+        .await
+        .map_err(|e| format!("Failed to update history entry: {}", e))
+}
+
+#[tauri::command]
+async fn export_history(
+    path: String,
+    format: HistoryFormat,
+    filter: Option<HistoryFilter>,
+) -> Result<usize, String> {
+    history_io::export_history(
+        std::path::Path::new(&path),
+        format,
+        &filter.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| format!("Failed to export translation history: {}", e))
+}
+
+#[tauri::command]
+async fn import_history(
+    path: String,
+    format: HistoryFormat,
+    filter: Option<HistoryFilter>,
+) -> Result<ImportSummary, String> {
+    history_io::import_history(
+        std::path::Path::new(&path),
+        format,
+        &filter.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| format!("Failed to import translation history: {}", e))
+}
+
+#[tauri::command]
+async fn export_settings(
+    path: String,
+    include_secrets: bool,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let config = state.config.lock().await.clone();
+    settings_bundle::export_settings(std::path::Path::new(&path), &config, include_secrets)
+        .map_err(|e| format!("Failed to export settings: {}", e))
+}
+
+/// Lists what importing the bundle would change without writing anything
+#[tauri::command]
+async fn preview_settings_import(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<SettingsImportPreview, String> {
+    let config = state.config.lock().await.clone();
+    settings_bundle::preview_import(std::path::Path::new(&path), &config)
+        .map_err(|e| format!("Failed to read settings bundle: {}", e))
+}
+
+#[tauri::command]
+async fn import_settings(
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<SettingsImportPreview, String> {
+    let current = state.config.lock().await.clone();
+    let (config, preview) = settings_bundle::import_settings(std::path::Path::new(&path), &current)
+        .map_err(|e| format!("Failed to import settings: {}", e))?;
+    config
+        .save()
+        .map_err(|e| format!("Failed to save config: {}", e))?;
+
+    // Applies the new settings the same way as an edit of config.json on disk
+    reload_config(&app).await;
+    refresh_tray_menu(&app, &config);
+    Ok(preview)
+}
+
+#[tauri::command]
+async fn get_usage_stats(filter: Option<HistoryFilter>) -> Result<UsageStats, String> {
+    stats::get_usage_stats(&filter.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to compute usage statistics: {}", e))
+}
+
+#[tauri::command]
+async fn get_encryption_status() -> Result<EncryptionStatus, String> {
+    Ok(vault::status())
+}
+
+// Writes the config, history and profiles again, encrypted or not as the vault is set up
+async fn rewrite_storage(
+    config: &Config,
+    history: &TranslationHistory,
+    profiles: &[profiles::Profile],
+) -> anyhow::Result<()> {
+    config.save()?;
+    history::save_history(history).await?;
+    profiles::save_all(profiles)
+}
+
+#[tauri::command]
+async fn enable_encryption(passphrase: String, state: State<'_, AppState>) -> Result<(), String> {
+    if vault::is_enabled() {
+        return Err("Encryption is already enabled".to_string());
+    }
+
+    // Read the history while it is still stored in plaintext
+    let history = history::load_history()
+        .await
+        .map_err(|e| format!("Failed to read translation history: {}", e))?;
+    let profiles = profiles::load_all().map_err(|e| format!("Failed to read profiles: {}", e))?;
+
+    let header = vault::create(&passphrase)
+        .await
+        .map_err(|e| format!("Failed to enable encryption: {}", e))?;
+
+    let mut config = state.config.lock().await;
+    config.encryption = Some(header);
+    let result = rewrite_storage(&config, &history, &profiles).await;
+
+    if let Err(e) = result {
+        // Roll back so the files on disk stay readable without a passphrase
+        vault::disable();
+        config.encryption = None;
+        if let Err(e) = rewrite_storage(&config, &history, &profiles).await {
+            log::error!("Failed to restore plaintext storage: {}", e);
+        }
+        return Err(format!("Failed to encrypt storage: {}", e));
+    }
+
+    log::info!("Encryption enabled for config secrets and history");
     Ok(())
 }
 
-fn parse_hotkey(hotkey: &str) -> Option<tauri_plugin_global_shortcut::Shortcut> {
-    use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut};
-
-    let parts: Vec<&str> = hotkey.split('+').map(|s| s.trim()).collect();
-    if parts.is_empty() {
-        return None;
-    }
-
-    let mut modifiers = Modifiers::empty();
-    let mut code = None;
-
-    for (i, part) in parts.iter().enumerate() {
-        // The last part should be the key code
-        if i == parts.len() - 1 {
-            if part.len() == 1 {
-                // Single character key
-                let c = part.chars().next().unwrap().to_uppercase().next().unwrap();
-                code = match c {
-                    'A' => Some(Code::KeyA),
-                    'B' => Some(Code::KeyB),
-                    'C' => Some(Code::KeyC),
-                    'D' => Some(Code::KeyD),
-                    'E' => Some(Code::KeyE),
-                    'F' => Some(Code::KeyF),
-                    'G' => Some(Code::KeyG),
-                    'H' => Some(Code::KeyH),
-                    'I' => Some(Code::KeyI),
-                    'J' => Some(Code::KeyJ),
-                    'K' => Some(Code::KeyK),
-                    'L' => Some(Code::KeyL),
-                    'M' => Some(Code::KeyM),
-                    'N' => Some(Code::KeyN),
-                    'O' => Some(Code::KeyO),
-                    'P' => Some(Code::KeyP),
-                    'Q' => Some(Code::KeyQ),
-                    'R' => Some(Code::KeyR),
-                    'S' => Some(Code::KeyS),
-                    'T' => Some(Code::KeyT),
-                    'U' => Some(Code::KeyU),
-                    'V' => Some(Code::KeyV),
-                    'W' => Some(Code::KeyW),
-                    'X' => Some(Code::KeyX),
-                    'Y' => Some(Code::KeyY),
-                    'Z' => Some(Code::KeyZ),
-                    '0' => Some(Code::Digit0),
-                    '1' => Some(Code::Digit1),
-                    '2' => Some(Code::Digit2),
-                    '3' => Some(Code::Digit3),
-                    '4' => Some(Code::Digit4),
-                    '5' => Some(Code::Digit5),
-                    '6' => Some(Code::Digit6),
-                    '7' => Some(Code::Digit7),
-                    '8' => Some(Code::Digit8),
-                    '9' => Some(Code::Digit9),
-                    _ => None,
-                };
-            } else {
-                // Special keys or function keys
-                code = match part.to_lowercase().as_str() {
-                    "f1" => Some(Code::F1),
-                    "f2" => Some(Code::F2),
-                    "f3" => Some(Code::F3),
-                    "f4" => Some(Code::F4),
-                    "f5" => Some(Code::F5),
-                    "f6" => Some(Code::F6),
-                    "f7" => Some(Code::F7),
-                    "f8" => Some(Code::F8),
-                    "f9" => Some(Code::F9),
-                    "f10" => Some(Code::F10),
-                    "f11" => Some(Code::F11),
-                    "f12" => Some(Code::F12),
-                    "space" => Some(Code::Space),
-                    "tab" => Some(Code::Tab),
-                    "escape" => Some(Code::Escape),
-                    "enter" => Some(Code::Enter),
-                    "backspace" => Some(Code::Backspace),
-                    "insert" => Some(Code::Insert),
-                    "delete" => Some(Code::Delete),
-                    "home" => Some(Code::Home),
-                    "end" => Some(Code::End),
-                    "pageup" => Some(Code::PageUp),
-                    "pagedown" => Some(Code::PageDown),
-                    "left" => Some(Code::ArrowLeft),
-                    "right" => Some(Code::ArrowRight),
-                    "up" => Some(Code::ArrowUp),
-                    "down" => Some(Code::ArrowDown),
-                    _ => None,
-                };
+#[tauri::command]
+async fn unlock_storage(passphrase: String, state: State<'_, AppState>) -> Result<(), String> {
+    vault::unlock(&passphrase)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut config = state.config.lock().await;
+    config
+        .decrypt_secrets()
+        .map_err(|e| format!("Failed to decrypt config secrets: {}", e))?;
+
+    // Saving again encrypts any secrets or history that are still stored in plaintext
+    if let Err(e) = config.save() {
+        log::error!("Failed to migrate config to encrypted storage: {}", e);
+    }
+    match history::load_history().await {
+        Ok(history) => {
+            if let Err(e) = history::save_history(&history).await {
+                log::error!("Failed to migrate history to encrypted storage: {}", e);
             }
-        } else {
-            // This part should be a modifier
-            match part.to_lowercase().as_str() {
-                "ctrl" | "control" | "commandorcontrol" => {
-                    modifiers |= Modifiers::CONTROL;
-                }
-                "alt" | "option" => {
-                    modifiers |= Modifiers::ALT;
-                }
-                "shift" => {
-                    modifiers |= Modifiers::SHIFT;
-                }
-                "super" | "command" | "cmd" | "meta" => {
-                    modifiers |= Modifiers::SUPER;
-                }
-                _ => {
-                    log::warn!("Unknown modifier: {}", part);
-                    return None;
-                }
+        }
+        Err(e) => log::error!("Failed to read translation history: {}", e),
+    }
+    if let Err(e) = profiles::load_all().and_then(|profiles| profiles::save_all(&profiles)) {
+        log::error!("Failed to migrate profiles to encrypted storage: {}", e);
+    }
+
+    let mut service = state.translation_service.lock().await;
+    *service = TranslationService::new(config.clone());
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn lock_storage(state: State<'_, AppState>) -> Result<(), String> {
+    if !vault::is_enabled() {
+        return Err("Encryption is not enabled".to_string());
+    }
+    vault::lock();
+
+    // Drop the decrypted secrets from memory by reloading the encrypted config
+    let stored = config_layers::load().map_err(|e| format!("Failed to reload config: {}", e))?;
+    let mut config = state.config.lock().await;
+    *config = stored;
+
+    let mut service = state.translation_service.lock().await;
+    *service = TranslationService::new(config.clone());
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn disable_encryption(passphrase: String, state: State<'_, AppState>) -> Result<(), String> {
+    vault::unlock(&passphrase)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let history = history::load_history()
+        .await
+        .map_err(|e| format!("Failed to decrypt translation history: {}", e))?;
+    let profiles =
+        profiles::load_all().map_err(|e| format!("Failed to decrypt profiles: {}", e))?;
+    let mut config = state.config.lock().await;
+    config
+        .decrypt_secrets()
+        .map_err(|e| format!("Failed to decrypt config secrets: {}", e))?;
+
+    vault::disable();
+    config.encryption = None;
+    config
+        .save()
+        .map_err(|e| format!("Failed to save config: {}", e))?;
+    history::save_history(&history)
+        .await
+        .map_err(|e| format!("Failed to save translation history: {}", e))?;
+    profiles::save_all(&profiles).map_err(|e| format!("Failed to save profiles: {}", e))?;
+
+    log::info!("Encryption disabled, config secrets and history stored in plaintext");
+    Ok(())
+}
+
+#[tauri::command]
+async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<ProfileSummary>, String> {
+    let config = state.config.lock().await;
+    profiles::list_profiles(config.active_profile.as_deref())
+        .map_err(|e| format!("Failed to read profiles: {}", e))
+}
+
+/// Saves the current provider, credential, language and prompt settings as a new profile
+#[tauri::command]
+async fn create_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ProfileSummary, String> {
+    let config = state.config.lock().await.clone();
+    let summary = profiles::create_profile(&name, &config)
+        .map_err(|e| format!("Failed to create profile: {}", e))?;
+    refresh_tray_menu(&app, &config);
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn duplicate_profile(
+    name: String,
+    new_name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ProfileSummary, String> {
+    let summary = profiles::duplicate_profile(&name, &new_name)
+        .map_err(|e| format!("Failed to duplicate profile: {}", e))?;
+    refresh_tray_menu(&app, &*state.config.lock().await);
+    Ok(summary)
+}
+
+/// Deletes a profile; the current settings stay in place if it was the active one
+#[tauri::command]
+async fn delete_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    profiles::delete_profile(&name).map_err(|e| format!("Failed to delete profile: {}", e))?;
+
+    let mut config = state.config.lock().await;
+    if config.active_profile.as_deref() == Some(name.as_str()) {
+        config.active_profile = None;
+        config
+            .save()
+            .map_err(|e| format!("Failed to save config: {}", e))?;
+    }
+    refresh_tray_menu(&app, &config);
+    Ok(())
+}
+
+#[tauri::command]
+async fn activate_profile(name: String, app: AppHandle) -> Result<Config, String> {
+    switch_profile(&app, &name)
+        .await
+        .map_err(|e| format!("Failed to activate profile: {}", e))
+}
+
+/// Applies a profile to the active config and rebuilds the translation service,
+/// shared by the `activate_profile` command and the tray menu
+pub(crate) async fn switch_profile(app: &AppHandle, name: &str) -> anyhow::Result<Config> {
+    let profile = profiles::get_profile(name)?;
+    let state = app.state::<AppState>();
+
+    let mut config = state.config.lock().await;
+    let mut updated = config.clone();
+    profile.apply_to(&mut updated);
+    let updated = config_layers::reapply(&updated)?;
+    updated.save()?;
+    *config = updated.clone();
+
+    let mut service = state.translation_service.lock().await;
+    *service = TranslationService::new(updated.clone());
+    drop(service);
+    drop(config);
+
+    log::info!("Switched to profile \"{}\"", name);
+    refresh_tray_menu(app, &updated);
+    if let Err(e) = app.emit("profile-changed", name) {
+        log::error!("Failed to emit profile change: {}", e);
+    }
+    Ok(updated)
+}
+
+#[derive(Clone, Serialize)]
+struct ConfigChangedEvent {
+    config: Config,
+    problems: Vec<ConfigProblem>,
+}
+
+/// Reloads config.json after it was changed on disk and applies the differences live,
+/// called by the config watcher
+pub(crate) async fn reload_config(app: &AppHandle) {
+    let state = app.state::<AppState>();
+
+    // An unreadable file is reported and the running config is kept
+    let mut loaded = match config_layers::load() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Ignoring change to config.json: {}", e);
+            *state.config_error.lock().await = Some(e.to_string());
+            if let Err(e) = app.emit("config-error", e.to_string()) {
+                log::error!("Failed to emit config error: {}", e);
             }
+            return;
         }
+    };
+    let decrypted = if vault::status().unlocked {
+        loaded.decrypt_secrets()
+    } else {
+        Ok(())
+    };
+    if let Err(e) = decrypted {
+        log::error!("Ignoring change to config.json: {}", e);
+        return;
     }
+    *state.config_error.lock().await = None;
 
-    if let Some(code) = code {
-        Some(Shortcut::new(Some(modifiers), code))
+    let mut config = state.config.lock().await;
+    // Writes made by the app itself show up here too and need no further work
+    if serde_json::to_value(&*config).ok() == serde_json::to_value(&loaded).ok() {
+        return;
+    }
+    let old_config = std::mem::replace(&mut *config, loaded.clone());
+    drop(config);
+
+    let mut service = state.translation_service.lock().await;
+    *service = TranslationService::new(loaded.clone());
+    drop(service);
+
+    if old_config.hotkeys != loaded.hotkeys || old_config.hotkey_chords != loaded.hotkey_chords {
+        setup_global_shortcut(app, &loaded)
+            .await
+            .unwrap_or_else(|e| log::error!("Failed to update global shortcut: {}", e));
+    }
+    if old_config.auto_start != loaded.auto_start {
+        set_autostart(app, loaded.auto_start);
+    }
+    if old_config.active_profile != loaded.active_profile
+        || old_config.clipboard_watch.enabled != loaded.clipboard_watch.enabled
+    {
+        refresh_tray_menu(app, &loaded);
+    }
+
+    let problems = loaded.validate();
+    for problem in &problems {
+        log::warn!("config.json: {}: {}", problem.field, problem.message);
+    }
+    log::info!("Reloaded config.json after it changed on disk");
+
+    let event = ConfigChangedEvent {
+        config: loaded,
+        problems,
+    };
+    if let Err(e) = app.emit("config-changed", event) {
+        log::error!("Failed to emit config change: {}", e);
+    }
+}
+
+fn set_autostart(app: &AppHandle, enabled: bool) {
+    let autostart = app.autolaunch();
+    let result = if enabled {
+        autostart.enable()
     } else {
-        None
+        autostart.disable()
+    };
+    if let Err(e) = result {
+        log::error!("Failed to update autostart: {}", e);
+    }
+}
+
+fn refresh_tray_menu(app: &AppHandle, config: &Config) {
+    if let Err(e) = tray::refresh_menu(app, config) {
+        log::error!("Failed to update tray menu: {}", e);
     }
 }
 
+#[tauri::command]
+async fn reset_detected_language() -> Result<(), String> {
+    log::info!("Detected language reset requested");
+    Ok(())
+}
+
+// Registers every configured binding and sends the result to the frontend; the error
+// lists the bindings that failed
 async fn setup_global_shortcut(
     app: &AppHandle,
     config: &Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut};
-
-    // Parse the hotkey from config
-    let shortcut = parse_hotkey(&config.hotkey).unwrap_or_else(|| {
-        log::warn!(
-            "Invalid hotkey format: {}, using default Ctrl+Alt+C",
-            config.hotkey
-        );
-        Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyC)
-    }); // Unregister any existing shortcuts
-    if let Err(e) = app.global_shortcut().unregister_all() {
-        log::warn!("Failed to unregister existing shortcuts: {}", e);
-    }
-
-    let app_handle = app.clone();
-    let hotkey_str = config.hotkey.clone();
-    app.global_shortcut()
-        .on_shortcut(shortcut, move |_app_handle, _shortcut, event| {
-            log::info!(
-                "Global shortcut triggered: {} - Event: {:?}",
-                hotkey_str,
-                event
-            );
-            let app_clone = app_handle.clone();
-            tauri::async_runtime::spawn(async move {
-                handle_shortcut_activation(app_clone).await;
-            });
-        })?;
+    let statuses = hotkeys::apply_bindings(app, &config.hotkeys);
+    if let Err(e) = app.emit("hotkey-status", &statuses) {
+        log::error!("Failed to emit hotkey status: {}", e);
+    }
 
-    log::info!("Global shortcut registered: {}", config.hotkey);
-    Ok(())
+    let mut failed: Vec<String> = statuses
+        .into_iter()
+        .filter_map(|status| {
+            let error = status.error?;
+            Some(format!(
+                "{} ({}): {}",
+                status.action.label(),
+                status.shortcut,
+                error
+            ))
+        })
+        .collect();
+    failed.extend(
+        chords::apply_chords(app, &config.hotkey_chords, &config.hotkeys)
+            .into_iter()
+            .filter_map(|status| {
+                let error = status.error?;
+                Some(format!("{} then {}: {}", status.leader, status.key, error))
+            }),
+    );
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failed.join("; ").into())
+    }
+}
+
+/// Runs a hotkey or chord action; `target_language` replaces the configured one for
+/// translate actions
+pub(crate) async fn run_hotkey_action(
+    app: &AppHandle,
+    action: HotkeyAction,
+    target_language: Option<String>,
+) {
+    let Some(window) = app.get_webview_window("main") else {
+        log::error!("Main window not found");
+        return;
+    };
+
+    match action {
+        HotkeyAction::TranslateClipboard | HotkeyAction::TranslateAlternative
+            if target_language.is_some() =>
+        {
+            let _ = window.emit("reset-detected-language", ());
+            capture_clipboard_to(app, &window, target_language).await;
+        }
+        HotkeyAction::TranslateClipboard => handle_shortcut_activation(app.clone()).await,
+        HotkeyAction::TranslateAlternative => {
+            let target_language = app
+                .state::<AppState>()
+                .config
+                .lock()
+                .await
+                .alternative_target_language
+                .clone();
+            let _ = window.emit("reset-detected-language", ());
+            capture_clipboard_to(app, &window, Some(target_language)).await;
+        }
+        HotkeyAction::ToggleWindow => {
+            let visible = window.is_visible().unwrap_or(false);
+            let focused = window.is_focused().unwrap_or(false);
+            if visible && focused {
+                let _ = window.hide();
+            } else {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        HotkeyAction::OpenHistory => {
+            let _ = window.show();
+            let _ = window.set_focus();
+            let _ = window.emit("open-history", ());
+        }
+        HotkeyAction::TranslateReplace => replace_selection(app, target_language).await,
+        HotkeyAction::ToggleClipboardWatch => {
+            clipboard_watcher::toggle_paused();
+            refresh_tray_menu(app, &*app.state::<AppState>().config.lock().await);
+        }
+    }
+}
+
+// Translates the selection in the focused application in place
+async fn replace_selection(app: &AppHandle, target_language: Option<String>) {
+    let mut config = app.state::<AppState>().config.lock().await.clone();
+    if let Some(target_language) = target_language {
+        config.target_language = target_language;
+    }
+
+    let clipboard = clipboard::AppClipboard::new(app);
+    let selection_clipboard = &clipboard;
+    // Copied HTML is sent with segment markers, so the history gets the plain texts
+    // once the replace is done
+    let translation = std::sync::Mutex::new(None);
+    let translation_slot = &translation;
+    let outcome = replace::translate_and_replace(
+        &clipboard,
+        &input::SystemKeyboard,
+        replace::ReplaceTiming::default(),
+        |text| async move {
+            // The selection sits on the clipboard while it is translated
+            if let Ok(Some(selection)) = selection_clipboard.read_text() {
+                clipboard_watcher::note_own_write(&selection);
+            }
+            let response = translation::translate_with_config(text, CaptureSource::Hotkey, config)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            notify_budget_warning(app, response.budget_warning.as_deref());
+            let translated = response.translated_text.clone();
+            *translation_slot.lock().unwrap() = Some(response);
+            Ok(translated)
+        },
+    )
+    .await;
+
+    let failure = match outcome {
+        Ok(replace::ReplaceOutcome::Replaced {
+            original,
+            translated,
+        }) => {
+            if let Some(mut response) = translation.into_inner().unwrap() {
+                response.original_text = original;
+                response.translated_text = translated;
+                if let Err(e) = add_translation_to_history(&response, None).await {
+                    log::error!("Failed to add translation to history: {}", e);
+                }
+            }
+            log::info!("Replaced the selection with its translation");
+            return;
+        }
+        Ok(replace::ReplaceOutcome::NothingSelected) => {
+            "Select the text to translate first".to_string()
+        }
+        Err(e) => e.to_string(),
+    };
+    log::warn!("Translate and replace failed: {}", failure);
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("GPTranslate could not replace the selection")
+        .body(failure)
+        .show()
+    {
+        log::error!("Failed to show replace notification: {}", e);
+    }
 }
 
 async fn handle_shortcut_activation(app: AppHandle) {
@@ -462,18 +1115,55 @@ async fn handle_shortcut_activation(app: AppHandle) {
 }
 
 async fn handle_clipboard_capture(app: &AppHandle, window: &tauri::WebviewWindow) {
-    // Add a small delay to ensure clipboard is updated
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    capture_clipboard_to(app, window, None).await;
+}
+
+/// Clipboard text sent to the frontend for translating into a language other than the configured one
+#[derive(Serialize, Clone)]
+struct TargetedClipboardText {
+    text: String,
+    target_language: String,
+}
 
-    match app.clipboard().read_text() {
+async fn capture_clipboard_to(
+    app: &AppHandle,
+    window: &tauri::WebviewWindow,
+    target_language: Option<String>,
+) {
+    // The copy happened before the hotkey, so the clipboard is read as it is
+    let app_clipboard = clipboard::AppClipboard::new(app);
+
+    // Only Linux has a PRIMARY selection, elsewhere the setting is ignored
+    let source = match app
+        .state::<AppState>()
+        .config
+        .lock()
+        .await
+        .capture_selection
+    {
+        source if cfg!(target_os = "linux") => source,
+        _ => clipboard::CaptureSelection::Clipboard,
+    };
+    let captured =
+        clipboard::read_capture_text(&app_clipboard, &clipboard::SystemSelection, source);
+    match captured {
         Ok(text) => {
-            if !text.trim().is_empty() {
+            if let Some(text) = text {
                 // Show window
                 let _ = window.show();
                 let _ = window.set_focus();
 
                 // Emit clipboard text event to frontend
-                let _ = window.emit("clipboard-text", &text);
+                let _ = match target_language {
+                    Some(target_language) => window.emit(
+                        "clipboard-text-target",
+                        TargetedClipboardText {
+                            text: text.clone(),
+                            target_language,
+                        },
+                    ),
+                    None => window.emit("clipboard-text", &text),
+                };
                 log::info!("Clipboard text sent to frontend: {}", text);
             } else {
                 log::warn!("Clipboard is empty");
@@ -495,15 +1185,29 @@ async fn handle_clipboard_capture(app: &AppHandle, window: &tauri::WebviewWindow
 pub fn run() {
     env_logger::init();
 
-    let config = Config::load().unwrap_or_else(|e| {
-        log::warn!("Failed to load config, using default: {}", e);
-        Config::default()
-    });
+    // A config that fails to load is reported to the user instead of being replaced,
+    // the defaults are only used in memory until settings are saved again
+    let (config, config_error) = match config_layers::load() {
+        Ok(config) => (config, None),
+        Err(e) => {
+            log::error!(
+                "Failed to load config, using defaults for this session: {}",
+                e
+            );
+            (config_layers::load_defaults(), Some(e.to_string()))
+        }
+    };
+
+    vault::init(config.encryption.clone());
+    if vault::is_enabled() {
+        log::info!("Encrypted storage is enabled and locked until unlocked by the user");
+    }
 
     let translation_service = TranslationService::new(config.clone());
     let app_state = AppState {
         config: Arc::new(Mutex::new(config.clone())),
         translation_service: Arc::new(Mutex::new(translation_service)),
+        config_error: Arc::new(Mutex::new(config_error.clone())),
     };
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -560,15 +1264,37 @@ pub fn run() {
         })
         .setup(move |app| {
             // Create system tray
-            if let Err(e) = tray::create_tray(app.handle()) {
+            if let Err(e) = tray::create_tray(app.handle(), &config) {
                 log::error!("Failed to create tray: {}", e);
             } // Setup global shortcut
+
+            let notified = config_error.as_ref().map(|error| {
+                app.notification()
+                    .builder()
+                    .title("GPTranslate settings could not be loaded")
+                    .body(error)
+                    .show()
+            });
+            if let Some(Err(e)) = notified {
+                log::error!("Failed to show config error notification: {}", e);
+            }
+
             let config_clone = config.clone();
             let app_handle = app.handle().clone();
 
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = setup_global_shortcut(&app_handle, &config_clone).await {
                     log::error!("Failed to setup global shortcut: {}", e);
+                    // Otherwise a dead hotkey goes unnoticed until the user presses it
+                    if let Err(e) = app_handle
+                        .notification()
+                        .builder()
+                        .title("GPTranslate hotkey unavailable")
+                        .body(e.to_string())
+                        .show()
+                    {
+                        log::error!("Failed to show hotkey notification: {}", e);
+                    }
                 }
             });
 
@@ -580,6 +1306,11 @@ pub fn run() {
                 }
             }
 
+            if let Err(e) = config_watcher::start(app.handle().clone()) {
+                log::error!("Failed to watch config file: {}", e);
+            }
+            clipboard_watcher::start(app.handle().clone());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -587,13 +1318,41 @@ pub fn run() {
             get_clipboard_text,
             translate,
             get_config,
+            get_config_error,
+            get_config_sources,
+            get_storage_paths,
+            validate_config,
             save_config,
+            get_hotkey_status,
+            validate_hotkey,
             copy_to_clipboard,
             test_translation_from_clipboard,
+            translate_clipboard_image,
+            translate_image,
+            estimate_translation_cost,
+            get_spending_summary,
             get_windows_theme,
             validate_api_key,
             get_translation_history_cmd,
             clear_translation_history_cmd,
+            retranslate_history_entry,
+            update_history_entry,
+            export_history,
+            import_history,
+            get_usage_stats,
+            export_settings,
+            preview_settings_import,
+            import_settings,
+            list_profiles,
+            create_profile,
+            duplicate_profile,
+            delete_profile,
+            activate_profile,
+            get_encryption_status,
+            enable_encryption,
+            unlock_storage,
+            lock_storage,
+            disable_encryption,
             reset_detected_language
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,320 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::clipboard::ClipboardImage;
+use crate::rich_text;
+
+// Used when no language is configured, Tesseract fails on an empty -l
+const DEFAULT_LANGUAGES: &str = "eng";
+
+// Tesseract's TSV level for single words, the other levels only repeat their boxes
+const WORD_LEVEL: u32 = 5;
+
+/// How the image button reads clipboard images
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageReader {
+    #[default]
+    Tesseract, // Offline, only the recognised text is sent
+    Model, // The image itself goes to a translation model that accepts images
+}
+
+/// Text recognition in clipboard images, done locally by Tesseract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct OcrSettings {
+    pub languages: String,      // Tesseract codes joined by "+", e.g. "eng+nor"
+    pub tesseract_path: String, // Empty to find tesseract on the PATH
+    pub tessdata_dir: String,   // Empty for the traineddata of the Tesseract installation
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            languages: DEFAULT_LANGUAGES.to_string(),
+            tesseract_path: String::new(),
+            tessdata_dir: String::new(),
+        }
+    }
+}
+
+/// Pixel rectangle in the image, for overlaying the translation
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    fn union(self, other: BoundingBox) -> BoundingBox {
+        if self.width == 0 && self.height == 0 {
+            return other;
+        }
+        let right = (self.left + self.width).max(other.left + other.width);
+        let bottom = (self.top + self.height).max(other.top + other.height);
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        BoundingBox {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcrLine {
+    pub text: String,
+    pub bounds: BoundingBox,
+    pub confidence: f32, // Mean word confidence from 0 to 100
+}
+
+/// A paragraph as laid out in the image, the unit that is translated and overlaid
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcrParagraph {
+    pub text: String,
+    pub bounds: BoundingBox,
+    pub lines: Vec<OcrLine>,
+    pub translated_text: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcrResult {
+    pub text: String, // Paragraphs separated by blank lines
+    pub width: usize,
+    pub height: usize,
+    pub paragraphs: Vec<OcrParagraph>,
+}
+
+struct Word {
+    paragraph: (u32, u32), // Block and paragraph number
+    line: u32,
+    bounds: BoundingBox,
+    confidence: f32,
+    text: String,
+}
+
+fn parse_word(row: &str) -> Option<Word> {
+    let columns: Vec<&str> = row.splitn(12, '\t').collect();
+    let [level, _page, block, paragraph, line, _word, left, top, width, height, confidence, text] =
+        columns.as_slice()
+    else {
+        return None;
+    };
+    if level.parse::<u32>().ok()? != WORD_LEVEL || text.trim().is_empty() {
+        return None;
+    }
+    Some(Word {
+        paragraph: (block.parse().ok()?, paragraph.parse().ok()?),
+        line: line.parse().ok()?,
+        bounds: BoundingBox {
+            left: left.parse().ok()?,
+            top: top.parse().ok()?,
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+        },
+        confidence: confidence.parse().ok()?,
+        text: text.trim().to_string(),
+    })
+}
+
+/// Groups the words of Tesseract's TSV output into lines and paragraphs
+pub fn parse_tsv(tsv: &str, width: usize, height: usize) -> OcrResult {
+    let mut paragraphs: Vec<OcrParagraph> = Vec::new();
+    let mut current: Option<((u32, u32), u32)> = None;
+    let mut line_words = 0;
+
+    for word in tsv.lines().skip(1).filter_map(parse_word) {
+        let new_paragraph = current.map(|(paragraph, _)| paragraph) != Some(word.paragraph);
+        let new_line = new_paragraph || current.map(|(_, line)| line) != Some(word.line);
+        current = Some((word.paragraph, word.line));
+
+        if new_paragraph {
+            paragraphs.push(OcrParagraph {
+                text: String::new(),
+                bounds: BoundingBox::default(),
+                lines: Vec::new(),
+                translated_text: None,
+            });
+        }
+        let paragraph = paragraphs.last_mut().unwrap();
+        if new_line {
+            paragraph.lines.push(OcrLine {
+                text: String::new(),
+                bounds: BoundingBox::default(),
+                confidence: 0.0,
+            });
+            line_words = 0;
+        }
+        let line = paragraph.lines.last_mut().unwrap();
+        if !line.text.is_empty() {
+            line.text.push(' ');
+        }
+        line.text.push_str(&word.text);
+        line.bounds = line.bounds.union(word.bounds);
+        line.confidence =
+            (line.confidence * line_words as f32 + word.confidence) / (line_words + 1) as f32;
+        line_words += 1;
+        paragraph.bounds = paragraph.bounds.union(word.bounds);
+    }
+
+    for paragraph in &mut paragraphs {
+        paragraph.text = paragraph
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    let text = paragraphs
+        .iter()
+        .map(|paragraph| paragraph.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    OcrResult {
+        text,
+        width,
+        height,
+        paragraphs,
+    }
+}
+
+fn languages(settings: &OcrSettings) -> &str {
+    match settings.languages.trim() {
+        "" => DEFAULT_LANGUAGES,
+        languages => languages,
+    }
+}
+
+/// Runs Tesseract on the image. Traineddata comes from the configured directory, else
+/// from the Tesseract installation
+pub async fn recognize(image: &ClipboardImage, settings: &OcrSettings) -> Result<OcrResult> {
+    let png = image.to_png()?;
+    let program = match settings.tesseract_path.trim() {
+        "" => "tesseract",
+        path => path,
+    };
+
+    let mut command = Command::new(program);
+    command.args(["stdin", "stdout", "-l", languages(settings)]);
+    match settings.tessdata_dir.trim() {
+        "" => {}
+        dir => {
+            command.arg("--tessdata-dir").arg(Path::new(dir));
+        }
+    }
+    let mut child = command
+        .arg("tsv")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not start {}, is Tesseract installed?", program))?;
+
+    let mut stdin = child.stdin.take().context("Tesseract has no input")?;
+    stdin.write_all(&png).await?;
+    drop(stdin);
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_tsv(
+        &String::from_utf8_lossy(&output.stdout),
+        image.width,
+        image.height,
+    ))
+}
+
+/// The text sent for translation. Several paragraphs are sent as marked segments in
+/// one request, so each can be overlaid with its own translation
+pub fn translation_request(result: &OcrResult) -> String {
+    match result.paragraphs.as_slice() {
+        [paragraph] => paragraph.text.clone(),
+        paragraphs => {
+            let texts: Vec<&str> = paragraphs.iter().map(|p| p.text.as_str()).collect();
+            rich_text::join_segments(&texts)
+        }
+    }
+}
+
+/// Hands the translation of `translation_request` out to the paragraphs and returns it
+/// as plain text. When the model lost the segment markers only the whole text is kept
+pub fn apply_translation(result: &mut OcrResult, translated: &str) -> String {
+    let count = result.paragraphs.len();
+    let segments = match count {
+        1 => Some(vec![translated.trim().to_string()]),
+        _ => rich_text::split_segments(translated, count),
+    };
+    match segments {
+        Some(segments) => {
+            for (paragraph, segment) in result.paragraphs.iter_mut().zip(&segments) {
+                paragraph.translated_text = Some(segment.clone());
+            }
+            segments.join("\n\n")
+        }
+        None => {
+            log::warn!("The translation lost the paragraph markers, boxes are not translated");
+            rich_text::strip_markers(translated)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_words_into_lines_and_paragraphs() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t400\t200\t-1\t
+2\t1\t1\t0\t0\t0\t10\t10\t200\t50\t-1\t
+5\t1\t1\t1\t1\t1\t10\t10\t60\t20\t96\tFilen
+5\t1\t1\t1\t1\t2\t80\t12\t40\t18\t90\tfinnes
+5\t1\t1\t1\t2\t1\t10\t40\t50\t20\t94\tikke
+5\t1\t2\t1\t1\t1\t10\t150\t30\t20\t88\tOK
+5\t1\t2\t1\t1\t2\t50\t150\t30\t20\t50\t ";
+        let mut result = parse_tsv(tsv, 400, 200);
+
+        assert_eq!(result.text, "Filen finnes\nikke\n\nOK");
+        assert_eq!(result.paragraphs.len(), 2);
+        let first = &result.paragraphs[0];
+        assert_eq!(first.lines.len(), 2);
+        assert_eq!(first.lines[0].confidence, 93.0);
+        assert_eq!(
+            first.bounds,
+            BoundingBox {
+                left: 10,
+                top: 10,
+                width: 110,
+                height: 50
+            }
+        );
+
+        let request = translation_request(&result);
+        assert_eq!(request, "⟦1⟧ Filen finnes\nikke\n⟦2⟧ OK");
+        let translated = apply_translation(&mut result, "⟦1⟧ The file does\nnot exist\n⟦2⟧ OK");
+        assert_eq!(translated, "The file does\nnot exist\n\nOK");
+        assert_eq!(result.paragraphs[1].translated_text.as_deref(), Some("OK"));
+    }
+
+    #[test]
+    fn falls_back_to_english_without_languages() {
+        let mut settings = OcrSettings {
+            languages: "  ".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(languages(&settings), "eng");
+        settings.languages = " eng+nor ".to_string();
+        assert_eq!(languages(&settings), "eng+nor");
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Points every directory at one location, e.g. for managed or test setups
+const HOME_ENV: &str = "GPTRANSLATE_HOME";
+
+// When this file sits next to the executable, all data is kept beside it
+const PORTABLE_MARKER: &str = "portable.txt";
+const PORTABLE_DATA_DIR: &str = "data";
+
+const APP_DIR_NAME: &str = "gptranslate";
+const LEGACY_DIR_NAME: &str = ".gptranslate";
+
+// Files moved to the config directory when migrating from ~/.gptranslate; everything
+// else, such as history and spending, goes to the data directory
+const CONFIG_FILE_PREFIXES: &[&str] = &["config.", "profiles."];
+
+lazy_static! {
+    static ref STORAGE: std::result::Result<StoragePaths, String> =
+        resolve().map_err(|e| e.to_string());
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageMode {
+    Environment, // GPTRANSLATE_HOME
+    Portable,
+    Xdg,
+    Legacy, // ~/.gptranslate, used on Windows and macOS
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoragePaths {
+    pub mode: StorageMode,
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+impl StoragePaths {
+    fn single(mode: StorageMode, dir: PathBuf) -> Self {
+        Self {
+            mode,
+            config_dir: dir.clone(),
+            data_dir: dir,
+        }
+    }
+}
+
+// Everything the directories depend on, gathered up front so resolution stays testable
+struct PathInputs {
+    home: Option<PathBuf>,                           // GPTRANSLATE_HOME
+    portable: Option<PathBuf>,                       // Data directory next to a marked executable
+    xdg: Option<(Option<PathBuf>, Option<PathBuf>)>, // XDG config and data bases, Linux only
+    legacy: Option<PathBuf>,                         // ~/.gptranslate
+}
+
+fn portable_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    exe_dir
+        .join(PORTABLE_MARKER)
+        .exists()
+        .then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+fn current_inputs() -> PathInputs {
+    PathInputs {
+        home: std::env::var_os(HOME_ENV)
+            .filter(|home| !home.is_empty())
+            .map(PathBuf::from),
+        portable: portable_dir(),
+        // dirs follows XDG_CONFIG_HOME and XDG_DATA_HOME
+        xdg: cfg!(target_os = "linux").then(|| (dirs::config_dir(), dirs::data_dir())),
+        legacy: dirs::home_dir().map(|home| home.join(LEGACY_DIR_NAME)),
+    }
+}
+
+fn resolve_paths(inputs: &PathInputs) -> Result<StoragePaths> {
+    if let Some(home) = &inputs.home {
+        return Ok(StoragePaths::single(StorageMode::Environment, home.clone()));
+    }
+    if let Some(dir) = &inputs.portable {
+        return Ok(StoragePaths::single(StorageMode::Portable, dir.clone()));
+    }
+    if let Some((config, data)) = &inputs.xdg {
+        let base = |dir: &Option<PathBuf>, name: &str| {
+            dir.as_ref()
+                .map(|dir| dir.join(APP_DIR_NAME))
+                .ok_or_else(|| anyhow::anyhow!("Could not find the XDG {} directory", name))
+        };
+        return Ok(StoragePaths {
+            mode: StorageMode::Xdg,
+            config_dir: base(config, "config")?,
+            data_dir: base(data, "data")?,
+        });
+    }
+    let legacy = inputs
+        .legacy
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(StoragePaths::single(StorageMode::Legacy, legacy))
+}
+
+// Only the XDG default takes over ~/.gptranslate. GPTRANSLATE_HOME and portable mode are
+// chosen for one setup, e.g. a USB stick or a test, and must not empty the regular install
+fn legacy_to_migrate<'a>(inputs: &'a PathInputs, paths: &StoragePaths) -> Option<&'a Path> {
+    inputs
+        .legacy
+        .as_deref()
+        .filter(|_| paths.mode == StorageMode::Xdg)
+}
+
+fn resolve() -> Result<StoragePaths> {
+    let inputs = current_inputs();
+    let paths = resolve_paths(&inputs)?;
+
+    if let Some(legacy) = legacy_to_migrate(&inputs, &paths) {
+        migrate_legacy_dir(legacy, &paths)
+            .unwrap_or_else(|e| log::error!("Failed to migrate {}: {}", LEGACY_DIR_NAME, e));
+    }
+    log::info!(
+        "Storing config in {:?} and data in {:?} ({:?} mode)",
+        paths.config_dir,
+        paths.data_dir,
+        paths.mode
+    );
+    Ok(paths)
+}
+
+// Moves files from ~/.gptranslate into the new directories, then removes the legacy
+// directory once it is empty. config.json moves last, so a config in the new place
+// means an earlier migration finished or the user already set the app up there
+fn migrate_legacy_dir(legacy: &Path, paths: &StoragePaths) -> Result<()> {
+    if !legacy.is_dir() || paths.config_dir.join("config.json").exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&paths.config_dir)?;
+    fs::create_dir_all(&paths.data_dir)?;
+    let mut entries = fs::read_dir(legacy)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name() == "config.json");
+    for entry in entries {
+        let name = entry.file_name();
+        let is_config = CONFIG_FILE_PREFIXES
+            .iter()
+            .any(|prefix| name.to_string_lossy().starts_with(prefix));
+        let target_dir = if is_config {
+            &paths.config_dir
+        } else {
+            &paths.data_dir
+        };
+        move_path(&entry.path(), &target_dir.join(&name))?;
+    }
+
+    fs::remove_dir(legacy)?;
+    log::info!(
+        "Moved {:?} to {:?} and {:?}",
+        legacy,
+        paths.config_dir,
+        paths.data_dir
+    );
+    Ok(())
+}
+
+// Falls back to copying when the XDG directories are on another file system
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)?;
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+pub fn storage_paths() -> Result<StoragePaths> {
+    STORAGE.clone().map_err(|e| anyhow::anyhow!(e))
+}
+
+fn ensure_dir(dir: PathBuf) -> Result<PathBuf> {
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Directory for config.json, profiles and config backups
+pub fn config_dir() -> Result<PathBuf> {
+    ensure_dir(storage_paths()?.config_dir)
+}
+
+/// Directory for history, spending and other data the app accumulates
+pub fn data_dir() -> Result<PathBuf> {
+    ensure_dir(storage_paths()?.data_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> PathInputs {
+        PathInputs {
+            home: None,
+            portable: None,
+            xdg: Some((
+                Some(PathBuf::from("/home/ola/.config")),
+                Some(PathBuf::from("/home/ola/.local/share")),
+            )),
+            legacy: Some(PathBuf::from("/home/ola/.gptranslate")),
+        }
+    }
+
+    #[test]
+    fn resolves_directories_by_precedence() {
+        let xdg = resolve_paths(&inputs()).unwrap();
+        assert_eq!(xdg.mode, StorageMode::Xdg);
+        assert_eq!(
+            xdg.config_dir,
+            PathBuf::from("/home/ola/.config/gptranslate")
+        );
+        assert_eq!(
+            xdg.data_dir,
+            PathBuf::from("/home/ola/.local/share/gptranslate")
+        );
+
+        let portable = resolve_paths(&PathInputs {
+            portable: Some(PathBuf::from("/opt/gptranslate/data")),
+            ..inputs()
+        })
+        .unwrap();
+        assert_eq!(portable.mode, StorageMode::Portable);
+        assert_eq!(portable.data_dir, portable.config_dir);
+
+        // GPTRANSLATE_HOME wins over a portable marker
+        let home = resolve_paths(&PathInputs {
+            home: Some(PathBuf::from("/srv/gptranslate")),
+            portable: Some(PathBuf::from("/opt/gptranslate/data")),
+            ..inputs()
+        })
+        .unwrap();
+        assert_eq!(home.mode, StorageMode::Environment);
+        assert_eq!(home.config_dir, PathBuf::from("/srv/gptranslate"));
+
+        let legacy = resolve_paths(&PathInputs {
+            xdg: None,
+            ..inputs()
+        })
+        .unwrap();
+        assert_eq!(legacy.mode, StorageMode::Legacy);
+        assert_eq!(legacy.data_dir, PathBuf::from("/home/ola/.gptranslate"));
+
+        assert!(resolve_paths(&PathInputs {
+            xdg: Some((None, None)),
+            ..inputs()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn only_the_xdg_default_takes_over_the_legacy_directory() {
+        let xdg = inputs();
+        let xdg_paths = resolve_paths(&xdg).unwrap();
+        assert_eq!(
+            legacy_to_migrate(&xdg, &xdg_paths),
+            Some(Path::new("/home/ola/.gptranslate"))
+        );
+
+        for explicit in [
+            PathInputs {
+                home: Some(PathBuf::from("/srv/gptranslate")),
+                ..inputs()
+            },
+            PathInputs {
+                portable: Some(PathBuf::from("/opt/gptranslate/data")),
+                ..inputs()
+            },
+            PathInputs {
+                xdg: None,
+                ..inputs()
+            },
+        ] {
+            let paths = resolve_paths(&explicit).unwrap();
+            assert_eq!(legacy_to_migrate(&explicit, &paths), None);
+        }
+    }
+
+    #[test]
+    fn finishes_an_interrupted_legacy_migration() {
+        let root = std::env::temp_dir().join(format!("gptranslate-{}", uuid::Uuid::new_v4()));
+        let legacy = root.join(".gptranslate");
+        let paths = StoragePaths {
+            mode: StorageMode::Xdg,
+            config_dir: root.join("config"),
+            data_dir: root.join("data"),
+        };
+        fs::create_dir_all(&legacy).unwrap();
+        fs::create_dir_all(&paths.data_dir).unwrap();
+        // An earlier run moved the history before it stopped
+        fs::write(paths.data_dir.join("history.json"), "{}").unwrap();
+        for name in ["config.json", "profiles.json", "spending.json"] {
+            fs::write(legacy.join(name), name).unwrap();
+        }
+
+        migrate_legacy_dir(&legacy, &paths).unwrap();
+        assert!(!legacy.exists());
+        assert!(paths.config_dir.join("config.json").exists());
+        assert!(paths.config_dir.join("profiles.json").exists());
+        assert!(paths.data_dir.join("spending.json").exists());
+        assert!(paths.data_dir.join("history.json").exists());
+
+        // A config already set up in the new place is left alone
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("config.json"), "old").unwrap();
+        migrate_legacy_dir(&legacy, &paths).unwrap();
+        assert_eq!(
+            fs::read_to_string(legacy.join("config.json")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            fs::read_to_string(paths.config_dir.join("config.json")).unwrap(),
+            "config.json"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
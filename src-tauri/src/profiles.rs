@@ -0,0 +1,401 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::config_layers;
+use crate::vault;
+
+lazy_static! {
+    // Serializes read-modify-write cycles on profiles.json
+    static ref PROFILES_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// A named set of provider, credential, language and prompt settings
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub api_provider: String,
+    pub openai_api_key: String,
+    pub azure_endpoint: String,
+    pub azure_api_key: String,
+    pub azure_api_version: String,
+    pub azure_deployment_name: String,
+    pub model: String,
+    pub target_language: String,
+    pub alternative_target_language: String,
+    pub custom_prompt: String,
+}
+
+/// Profile details safe to show in the UI, without credentials
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub api_provider: String,
+    pub model: String,
+    pub target_language: String,
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ProfileStore {
+    #[serde(default)]
+    profiles: Vec<Profile>,
+}
+
+impl Profile {
+    pub fn from_config(name: &str, config: &Config) -> Self {
+        Self {
+            name: name.to_string(),
+            api_provider: config.api_provider.clone(),
+            openai_api_key: config.openai_api_key.clone(),
+            azure_endpoint: config.azure_endpoint.clone(),
+            azure_api_key: config.azure_api_key.clone(),
+            azure_api_version: config.azure_api_version.clone(),
+            azure_deployment_name: config.azure_deployment_name.clone(),
+            model: config.model.clone(),
+            target_language: config.target_language.clone(),
+            alternative_target_language: config.alternative_target_language.clone(),
+            custom_prompt: config.custom_prompt.clone(),
+        }
+    }
+
+    /// Copies the profile settings into the config and marks the profile active
+    pub fn apply_to(&self, config: &mut Config) {
+        config.api_provider = self.api_provider.clone();
+        config.openai_api_key = self.openai_api_key.clone();
+        config.azure_endpoint = self.azure_endpoint.clone();
+        config.azure_api_key = self.azure_api_key.clone();
+        config.azure_api_version = self.azure_api_version.clone();
+        config.azure_deployment_name = self.azure_deployment_name.clone();
+        config.model = self.model.clone();
+        config.target_language = self.target_language.clone();
+        config.alternative_target_language = self.alternative_target_language.clone();
+        config.custom_prompt = self.custom_prompt.clone();
+        config.active_profile = Some(self.name.clone());
+    }
+
+    pub fn secrets_mut(&mut self) -> [&mut String; 2] {
+        [&mut self.openai_api_key, &mut self.azure_api_key]
+    }
+
+    fn summary(&self, active_profile: Option<&str>) -> ProfileSummary {
+        ProfileSummary {
+            name: self.name.clone(),
+            api_provider: self.api_provider.clone(),
+            model: self.model.clone(),
+            target_language: self.target_language.clone(),
+            active: active_profile == Some(self.name.as_str()),
+        }
+    }
+}
+
+impl ProfileStore {
+    fn add(&mut self, profile: Profile) -> Result<()> {
+        if self
+            .profiles
+            .iter()
+            .any(|existing| existing.name == profile.name)
+        {
+            return Err(anyhow::anyhow!(
+                "A profile named \"{}\" already exists",
+                profile.name
+            ));
+        }
+        self.profiles.push(profile);
+        Ok(())
+    }
+
+    fn duplicate(&mut self, name: &str, new_name: String) -> Result<ProfileSummary> {
+        let mut copy = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Profile \"{}\" does not exist", name))?;
+        copy.name = new_name;
+        let summary = copy.summary(None);
+        self.add(copy)?;
+        Ok(summary)
+    }
+
+    // Without secrets in the bundle, profiles that already exist keep their API keys
+    fn import(&mut self, incoming: Vec<Profile>, includes_secrets: bool) {
+        for mut profile in incoming {
+            match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+                Some(existing) => {
+                    if !includes_secrets {
+                        profile.openai_api_key = existing.openai_api_key.clone();
+                        // An Azure key only works for the endpoint it was issued for
+                        if profile.azure_endpoint == existing.azure_endpoint {
+                            profile.azure_api_key = existing.azure_api_key.clone();
+                        }
+                    }
+                    *existing = profile;
+                }
+                None => self.profiles.push(profile),
+            }
+        }
+    }
+}
+
+fn get_profiles_path() -> Result<PathBuf> {
+    Ok(Config::get_config_dir()?.join("profiles.json"))
+}
+
+fn load_store() -> Result<ProfileStore> {
+    let path = get_profiles_path()?;
+    if !path.exists() {
+        return Ok(ProfileStore::default());
+    }
+    let contents = fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+// API keys are encrypted the same way as in config.json when the vault is enabled
+fn save_store(store: &ProfileStore) -> Result<()> {
+    let mut stored = store.profiles.clone();
+    vault::encrypt_secrets(stored.iter_mut().flat_map(|profile| profile.secrets_mut()))?;
+
+    let path = get_profiles_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(
+        &temp_path,
+        serde_json::to_string_pretty(&ProfileStore { profiles: stored })?,
+    )?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Profile name must not be empty"));
+    }
+    Ok(name.to_string())
+}
+
+pub fn list_profiles(active_profile: Option<&str>) -> Result<Vec<ProfileSummary>> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    Ok(load_store()?
+        .profiles
+        .iter()
+        .map(|profile| profile.summary(active_profile))
+        .collect())
+}
+
+fn decrypt_secrets(profile: &mut Profile) -> Result<()> {
+    vault::decrypt_secrets(profile.secrets_mut())
+}
+
+/// Returns the profile with its API keys decrypted when the vault is unlocked
+pub fn get_profile(name: &str) -> Result<Profile> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut profile = load_store()?
+        .profiles
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Profile \"{}\" does not exist", name))?;
+    decrypt_secrets(&mut profile)?;
+    Ok(profile)
+}
+
+/// Reads every profile with its API keys decrypted, used when encryption is turned on or off
+pub fn load_all() -> Result<Vec<Profile>> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut profiles = load_store()?.profiles;
+    for profile in &mut profiles {
+        decrypt_secrets(profile)?;
+    }
+    Ok(profiles)
+}
+
+pub fn save_all(profiles: &[Profile]) -> Result<()> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    save_store(&ProfileStore {
+        profiles: profiles.to_vec(),
+    })
+}
+
+/// Creates a new profile from the current settings
+pub fn create_profile(name: &str, config: &Config) -> Result<ProfileSummary> {
+    let name = validate_name(name)?;
+    let config = &config_layers::saved_form(config)?;
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut store = load_store()?;
+    let profile = Profile::from_config(&name, config);
+    let summary = profile.summary(config.active_profile.as_deref());
+    store.add(profile)?;
+    save_store(&store)?;
+    Ok(summary)
+}
+
+pub fn duplicate_profile(name: &str, new_name: &str) -> Result<ProfileSummary> {
+    let new_name = validate_name(new_name)?;
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut store = load_store()?;
+    let summary = store.duplicate(name, new_name)?;
+    save_store(&store)?;
+    Ok(summary)
+}
+
+pub fn delete_profile(name: &str) -> Result<()> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut store = load_store()?;
+    let before = store.profiles.len();
+    store.profiles.retain(|profile| profile.name != name);
+    if store.profiles.len() == before {
+        return Err(anyhow::anyhow!("Profile \"{}\" does not exist", name));
+    }
+    save_store(&store)
+}
+
+/// Writes the settings of the active profile back after they were edited in the settings window
+pub fn update_active_profile(config: &Config) -> Result<()> {
+    let Some(name) = config.active_profile.as_deref() else {
+        return Ok(());
+    };
+    let config = &config_layers::saved_form(config)?;
+
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut store = load_store()?;
+    match store
+        .profiles
+        .iter_mut()
+        .find(|profile| profile.name == name)
+    {
+        Some(profile) => *profile = Profile::from_config(name, config),
+        None => return Ok(()),
+    }
+    save_store(&store)
+}
+
+/// Profiles for a settings bundle; API keys are decrypted or left out entirely
+pub fn export_profiles(include_secrets: bool) -> Result<Vec<Profile>> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut profiles = load_store()?.profiles;
+    for profile in &mut profiles {
+        if include_secrets {
+            decrypt_secrets(profile)?;
+        } else {
+            profile.secrets_mut().into_iter().for_each(String::clear);
+        }
+    }
+    Ok(profiles)
+}
+
+/// Adds or replaces profiles by name. Without secrets in the bundle, profiles that
+/// already exist keep their current API keys
+pub fn import_profiles(incoming: Vec<Profile>, includes_secrets: bool) -> Result<()> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut store = load_store()?;
+    store.import(incoming, includes_secrets);
+    save_store(&store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, azure_endpoint: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            api_provider: "azure_openai".to_string(),
+            openai_api_key: format!("sk-{}", name),
+            azure_endpoint: azure_endpoint.to_string(),
+            azure_api_key: format!("az-{}", name),
+            model: "gpt-4.1".to_string(),
+            target_language: "English".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn creates_and_duplicates_profiles_by_unique_name() {
+        let mut store = ProfileStore::default();
+        store.add(profile("Work", "https://work.example")).unwrap();
+        assert!(store.add(profile("Work", "https://other.example")).is_err());
+        assert!(validate_name("   ").is_err());
+        assert_eq!(validate_name(" Home ").unwrap(), "Home");
+
+        let summary = store.duplicate("Work", "Work copy".to_string()).unwrap();
+        assert_eq!(summary.name, "Work copy");
+        assert!(!summary.active);
+        assert_eq!(store.profiles[1].azure_api_key, "az-Work");
+        assert!(store.duplicate("Work", "Work copy".to_string()).is_err());
+        assert!(store.duplicate("Missing", "New".to_string()).is_err());
+        assert_eq!(store.profiles.len(), 2);
+    }
+
+    #[test]
+    fn switching_copies_the_profile_into_the_config() {
+        let mut config = Config {
+            target_language: "Norwegian".to_string(),
+            ..Config::default()
+        };
+        let hotkeys = config.hotkeys.clone();
+        profile("Work", "https://work.example").apply_to(&mut config);
+
+        assert_eq!(config.active_profile.as_deref(), Some("Work"));
+        assert_eq!(config.api_provider, "azure_openai");
+        assert_eq!(config.azure_api_key, "az-Work");
+        assert_eq!(config.target_language, "English");
+        // Settings outside the profile are left alone
+        assert_eq!(config.hotkeys, hotkeys);
+        assert_eq!(
+            Profile::from_config("Work", &config),
+            profile("Work", "https://work.example")
+        );
+    }
+
+    #[test]
+    fn imports_keep_api_keys_unless_the_endpoint_changed() {
+        let mut store = ProfileStore::default();
+        store.add(profile("Same", "https://same.example")).unwrap();
+        store.add(profile("Moved", "https://old.example")).unwrap();
+        store
+            .add(profile("Replaced", "https://same.example"))
+            .unwrap();
+
+        // Bundles exported without secrets carry empty keys
+        let without_keys = |name, endpoint| Profile {
+            openai_api_key: String::new(),
+            azure_api_key: String::new(),
+            model: "gpt-4.1-mini".to_string(),
+            ..profile(name, endpoint)
+        };
+        store.import(
+            vec![
+                without_keys("Same", "https://same.example"),
+                without_keys("Moved", "https://new.example"),
+                without_keys("New", "https://new.example"),
+            ],
+            false,
+        );
+        store.import(
+            vec![Profile {
+                openai_api_key: "sk-bundle".to_string(),
+                azure_api_key: String::new(),
+                ..profile("Replaced", "https://same.example")
+            }],
+            true,
+        );
+
+        let find = |name| store.profiles.iter().find(|p| p.name == name).unwrap();
+        assert_eq!(find("Same").model, "gpt-4.1-mini");
+        assert_eq!(find("Same").openai_api_key, "sk-Same");
+        assert_eq!(find("Same").azure_api_key, "az-Same");
+        // The Azure key belongs to the old endpoint and is not sent to the new one
+        assert_eq!(find("Moved").openai_api_key, "sk-Moved");
+        assert_eq!(find("Moved").azure_api_key, "");
+        assert_eq!(find("New").azure_api_key, "");
+        // A bundle with secrets replaces the keys, also with empty ones
+        assert_eq!(find("Replaced").openai_api_key, "sk-bundle");
+        assert_eq!(find("Replaced").azure_api_key, "");
+        assert_eq!(store.profiles.len(), 4);
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::clipboard::{self, ClipboardBackend};
+use crate::input::KeyboardInput;
+use crate::rich_text::{self, HtmlDocument};
+
+/// Delays of the replace pipeline; the defaults suit most applications
+#[derive(Clone, Copy, Debug)]
+pub struct ReplaceTiming {
+    pub poll_interval: Duration,
+    // How long the application gets to put the selection on the clipboard
+    pub copy_timeout: Duration,
+    // Time for the application to read the pasted text before the clipboard is restored
+    pub paste_delay: Duration,
+}
+
+impl Default for ReplaceTiming {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(20),
+            copy_timeout: Duration::from_millis(500),
+            paste_delay: Duration::from_millis(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaceOutcome {
+    Replaced {
+        original: String,
+        translated: String,
+    },
+    NothingSelected,
+}
+
+// Sending the key events waits for the hotkey's modifiers to be released, so it runs on
+// a blocking thread instead of holding up an async worker
+async fn send_keys<K>(keyboard: &K, send: fn(&K) -> Result<()>) -> Result<()>
+where
+    K: KeyboardInput + Clone + Send + 'static,
+{
+    let keyboard = keyboard.clone();
+    tauri::async_runtime::spawn_blocking(move || send(&keyboard)).await?
+}
+
+// Writes the translated HTML and its plain text, returning the plain text
+async fn translate_html<F, Fut>(
+    clipboard: &impl ClipboardBackend,
+    mut document: HtmlDocument,
+    translate: F,
+) -> Result<String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let texts = document.texts();
+    let count = texts.len();
+    let translated = translate(rich_text::join_segments(&texts)).await?;
+    match rich_text::split_segments(&translated, count) {
+        Some(segments) => {
+            document.set_texts(segments);
+            let plain = document.plain_text();
+            clipboard.write_html(&document.to_html(), &plain)?;
+            Ok(plain)
+        }
+        None => {
+            log::warn!("The translation lost the HTML segment markers, pasting plain text");
+            let plain = rich_text::strip_markers(&translated);
+            clipboard.write_text(&plain)?;
+            Ok(plain)
+        }
+    }
+}
+
+async fn replace_selection<K, F, Fut>(
+    clipboard: &impl ClipboardBackend,
+    keyboard: &K,
+    timing: ReplaceTiming,
+    translate: F,
+) -> Result<ReplaceOutcome>
+where
+    K: KeyboardInput + Clone + Send + 'static,
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    // Cleared first, so copying text that was already there still counts as a change
+    clipboard.write_text("")?;
+    let before = clipboard::change_marker(clipboard)?;
+    send_keys(keyboard, K::copy_selection).await?;
+    let copied = clipboard::wait_for_change(
+        clipboard,
+        &before,
+        timing.poll_interval,
+        timing.copy_timeout,
+    )
+    .await?;
+    let Some(original) = copied
+        .then(|| clipboard.read_text())
+        .transpose()?
+        .flatten()
+        .filter(|text| !text.trim().is_empty())
+    else {
+        return Ok(ReplaceOutcome::NothingSelected);
+    };
+
+    // Copied HTML is translated node by node so the pasted text keeps its formatting
+    let document = clipboard
+        .read_html()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to read the HTML on the clipboard: {}", e);
+            None
+        })
+        .map(|html| HtmlDocument::parse(&html))
+        .filter(|document| !document.texts().is_empty());
+    let translated = match document {
+        Some(document) => translate_html(clipboard, document, translate).await?,
+        None => {
+            let translated = translate(original.clone()).await?;
+            clipboard.write_text(&translated)?;
+            translated
+        }
+    };
+    send_keys(keyboard, K::paste).await?;
+    Ok(ReplaceOutcome::Replaced {
+        original,
+        translated,
+    })
+}
+
+/// Copies the selection in the focused application, translates it and pastes the
+/// translation over it. The clipboard is restored afterwards, also when a step fails
+pub async fn translate_and_replace<K, F, Fut>(
+    clipboard: &impl ClipboardBackend,
+    keyboard: &K,
+    timing: ReplaceTiming,
+    translate: F,
+) -> Result<ReplaceOutcome>
+where
+    K: KeyboardInput + Clone + Send + 'static,
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let saved = clipboard.snapshot()?;
+    let result = replace_selection(clipboard, keyboard, timing, translate).await;
+    if matches!(result, Ok(ReplaceOutcome::Replaced { .. })) {
+        sleep(timing.paste_delay).await;
+    }
+
+    if let Err(e) = clipboard.restore(&saved) {
+        log::error!("Failed to restore the clipboard: {}", e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::ClipboardSnapshot;
+    use std::sync::{Arc, Mutex};
+
+    // Plays the focused application: copying puts its selection on the clipboard and
+    // pasting records what the clipboard held, the HTML when there is some
+    #[derive(Default)]
+    struct FakeDesktop {
+        clipboard: Mutex<Option<String>>,
+        html: Mutex<Option<String>>,
+        selection: Option<String>,
+        selection_html: Option<String>,
+        pasted: Mutex<Vec<String>>,
+    }
+
+    impl ClipboardBackend for FakeDesktop {
+        fn read_text(&self) -> Result<Option<String>> {
+            Ok(self.clipboard.lock().unwrap().clone())
+        }
+
+        fn write_text(&self, text: &str) -> Result<()> {
+            *self.clipboard.lock().unwrap() = Some(text.to_string());
+            *self.html.lock().unwrap() = None;
+            Ok(())
+        }
+
+        fn read_html(&self) -> Result<Option<String>> {
+            Ok(self.html.lock().unwrap().clone())
+        }
+
+        fn write_html(&self, html: &str, alt_text: &str) -> Result<()> {
+            self.write_text(alt_text)?;
+            *self.html.lock().unwrap() = Some(html.to_string());
+            Ok(())
+        }
+
+        fn snapshot(&self) -> Result<ClipboardSnapshot> {
+            Ok(ClipboardSnapshot {
+                text: self.read_text()?,
+                html: self.html.lock().unwrap().clone(),
+                image: None,
+            })
+        }
+
+        fn restore(&self, snapshot: &ClipboardSnapshot) -> Result<()> {
+            *self.clipboard.lock().unwrap() = snapshot.text.clone();
+            *self.html.lock().unwrap() = snapshot.html.clone();
+            Ok(())
+        }
+    }
+
+    impl KeyboardInput for FakeDesktop {
+        fn copy_selection(&self) -> Result<()> {
+            if let Some(selection) = &self.selection {
+                self.write_text(selection)?;
+                *self.html.lock().unwrap() = self.selection_html.clone();
+            }
+            Ok(())
+        }
+
+        fn paste(&self) -> Result<()> {
+            let text = match self.read_html()? {
+                Some(html) => html,
+                None => self.read_text()?.unwrap_or_default(),
+            };
+            self.pasted.lock().unwrap().push(text);
+            Ok(())
+        }
+    }
+
+    fn timing() -> ReplaceTiming {
+        ReplaceTiming {
+            poll_interval: Duration::from_millis(1),
+            copy_timeout: Duration::from_millis(20),
+            paste_delay: Duration::ZERO,
+        }
+    }
+
+    fn desktop(clipboard: &str, selection: Option<&str>) -> FakeDesktop {
+        FakeDesktop {
+            clipboard: Mutex::new(Some(clipboard.to_string())),
+            selection: selection.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    // The keyboard is handed to a blocking thread, so the shared fake goes in an Arc
+    fn shared(desktop: FakeDesktop) -> Arc<FakeDesktop> {
+        Arc::new(desktop)
+    }
+
+    #[tokio::test]
+    async fn pastes_translation_and_restores_clipboard() {
+        let desktop = shared(desktop("saved", Some("hei verden")));
+        *desktop.html.lock().unwrap() = Some("<b>saved</b>".to_string());
+        let outcome = translate_and_replace(&*desktop, &desktop, timing(), |text| async move {
+            assert_eq!(text, "hei verden");
+            Ok("hello world".to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            outcome,
+            ReplaceOutcome::Replaced {
+                original: "hei verden".to_string(),
+                translated: "hello world".to_string(),
+            }
+        );
+        assert_eq!(*desktop.pasted.lock().unwrap(), vec!["hello world"]);
+        assert_eq!(desktop.read_text().unwrap().as_deref(), Some("saved"));
+        assert_eq!(
+            desktop.html.lock().unwrap().as_deref(),
+            Some("<b>saved</b>")
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_the_formatting_of_copied_html() {
+        let desktop = shared(FakeDesktop {
+            selection_html: Some("<p><b>Hei</b> verden</p>".to_string()),
+            ..desktop("saved", Some("Hei verden"))
+        });
+        let outcome = translate_and_replace(&*desktop, &desktop, timing(), |text| async move {
+            assert_eq!(text, "⟦1⟧ Hei\n⟦2⟧ verden");
+            Ok("⟦1⟧ Hello\n⟦2⟧ world".to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            outcome,
+            ReplaceOutcome::Replaced {
+                original: "Hei verden".to_string(),
+                translated: "Hello world".to_string(),
+            }
+        );
+        assert_eq!(
+            *desktop.pasted.lock().unwrap(),
+            vec!["<p><b>Hello</b> world</p>"]
+        );
+    }
+
+    #[tokio::test]
+    async fn pastes_plain_text_when_the_model_drops_a_marker() {
+        let desktop = shared(FakeDesktop {
+            selection_html: Some("<p><b>Hei</b> verden</p>".to_string()),
+            ..desktop("saved", Some("Hei verden"))
+        });
+        let outcome = translate_and_replace(&*desktop, &desktop, timing(), |_| async {
+            Ok("⟦1⟧ Hello world".to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            outcome,
+            ReplaceOutcome::Replaced {
+                original: "Hei verden".to_string(),
+                translated: "Hello world".to_string(),
+            }
+        );
+        assert_eq!(*desktop.pasted.lock().unwrap(), vec!["Hello world"]);
+    }
+
+    #[tokio::test]
+    async fn leaves_application_alone_without_selection_or_translation() {
+        let unselected = shared(desktop("saved", None));
+        let outcome = translate_and_replace(&*unselected, &unselected, timing(), |_| async {
+            Ok(String::new())
+        })
+        .await
+        .unwrap();
+        assert_eq!(outcome, ReplaceOutcome::NothingSelected);
+
+        let failing = shared(desktop("saved", Some("text")));
+        let result = translate_and_replace(&*failing, &failing, timing(), |_| async {
+            Err(anyhow::anyhow!("API error"))
+        })
+        .await;
+        assert!(result.is_err());
+
+        for desktop in [unselected, failing] {
+            assert!(desktop.pasted.lock().unwrap().is_empty());
+            assert_eq!(desktop.read_text().unwrap().as_deref(), Some("saved"));
+        }
+    }
+}
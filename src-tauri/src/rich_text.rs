@@ -0,0 +1,292 @@
+// Translation of copied HTML: the text between the tags is translated and the tags are
+// kept, so bold text, links and lists survive the round trip
+
+// Elements whose content is never shown as text
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "title", "template"];
+
+// Elements that start a new line in the plain-text flavour
+const BLOCK_ELEMENTS: [&str; 20] = [
+    "address",
+    "article",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dt",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "tr",
+    "ul",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Markup(String),
+    Text(String), // With entities decoded
+}
+
+/// Copied HTML split into markup and text nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlDocument {
+    parts: Vec<Part>,
+}
+
+fn tag_name(markup: &str) -> String {
+    markup
+        .trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+fn encode_entities(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl HtmlDocument {
+    pub fn parse(html: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = html;
+        let mut raw_text_end: Option<String> = None;
+
+        while !rest.is_empty() {
+            if let Some(closing) = &raw_text_end {
+                // Script and style content is kept as it is, up to the closing tag
+                let end = rest.to_ascii_lowercase().find(closing.as_str());
+                let end = end.unwrap_or(rest.len());
+                parts.push(Part::Markup(rest[..end].to_string()));
+                rest = &rest[end..];
+                raw_text_end = None;
+                continue;
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                parts.push(Part::Text(decode_entities(&rest[..end])));
+                rest = &rest[end..];
+                continue;
+            }
+
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->").map(|end| end + 3)
+            } else {
+                rest.find('>').map(|end| end + 1)
+            };
+            let end = end.unwrap_or(rest.len());
+            let markup = &rest[..end];
+            let name = tag_name(markup);
+            if !markup.starts_with("</") && RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+                raw_text_end = Some(format!("</{}", name));
+            }
+            parts.push(Part::Markup(markup.to_string()));
+            rest = &rest[end..];
+        }
+        Self { parts }
+    }
+
+    fn translatable(&self) -> impl Iterator<Item = &String> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Text(text) if !text.trim().is_empty() => Some(text),
+            _ => None,
+        })
+    }
+
+    /// The text nodes worth translating, without surrounding whitespace
+    pub fn texts(&self) -> Vec<&str> {
+        self.translatable().map(|text| text.trim()).collect()
+    }
+
+    /// Replaces the text nodes returned by `texts`, keeping their surrounding whitespace
+    pub fn set_texts(&mut self, translations: Vec<String>) {
+        let mut translations = translations.into_iter();
+        for part in &mut self.parts {
+            let Part::Text(text) = part else { continue };
+            if text.trim().is_empty() {
+                continue;
+            }
+            let Some(translation) = translations.next() else {
+                return;
+            };
+            let leading = &text[..text.len() - text.trim_start().len()];
+            let trailing = &text[text.trim_end().len()..];
+            *text = format!("{}{}{}", leading, translation.trim(), trailing);
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Markup(markup) => markup.clone(),
+                Part::Text(text) => encode_entities(text),
+            })
+            .collect()
+    }
+
+    /// The plain-text flavour written next to the HTML, with a line per block element
+    pub fn plain_text(&self) -> String {
+        let mut plain = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Markup(markup) if BLOCK_ELEMENTS.contains(&tag_name(markup).as_str()) => {
+                    plain.push('\n');
+                }
+                Part::Markup(_) => {}
+                Part::Text(text) => {
+                    // Whitespace in HTML source collapses to a single space
+                    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let starts_with_space = text.starts_with(char::is_whitespace);
+                    let ends_with_space = text.ends_with(char::is_whitespace);
+                    if starts_with_space && !plain.ends_with([' ', '\n']) && !plain.is_empty() {
+                        plain.push(' ');
+                    }
+                    plain.push_str(&collapsed);
+                    if ends_with_space && !collapsed.is_empty() {
+                        plain.push(' ');
+                    }
+                }
+            }
+        }
+        plain
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn marker(number: usize) -> String {
+    format!("⟦{}⟧", number)
+}
+
+/// Joins text nodes into one text for a single translation request, each starting with
+/// a numbered marker the model is expected to keep
+pub fn join_segments(texts: &[&str]) -> String {
+    texts
+        .iter()
+        .enumerate()
+        .map(|(index, text)| format!("{} {}", marker(index + 1), text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits a translation of `join_segments` back into its text nodes. Returns `None`
+/// when the model dropped or reordered a marker
+pub fn split_segments(translated: &str, count: usize) -> Option<Vec<String>> {
+    let mut segments = Vec::with_capacity(count);
+    let mut rest = translated.trim_start().strip_prefix(marker(1).as_str())?;
+    for number in 2..=count {
+        let end = rest.find(marker(number).as_str())?;
+        segments.push(rest[..end].trim().to_string());
+        rest = &rest[end + marker(number).len()..];
+    }
+    if rest.contains('⟦') {
+        return None;
+    }
+    segments.push(rest.trim().to_string());
+    Some(segments)
+}
+
+/// Removes the markers from a translation that could not be split
+pub fn strip_markers(translated: &str) -> String {
+    let mut stripped = String::with_capacity(translated.len());
+    let mut rest = translated;
+    while let Some(start) = rest.find('⟦') {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find('⟧') {
+            Some(end) => rest[start + end + '⟧'.len_utf8()..].trim_start_matches(' '),
+            None => &rest[start + '⟦'.len_utf8()..],
+        };
+    }
+    stripped.push_str(rest);
+    stripped.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_text_nodes_and_keeps_tags() {
+        let html = "<ul><li><b>Hei</b> verden</li>\n<li><a href=\"https://x.no?a=1&amp;b=2\">Les &amp; skriv</a></li></ul><style>li { color: red }</style>";
+        let mut document = HtmlDocument::parse(html);
+        assert_eq!(document.to_html(), html);
+        assert_eq!(document.texts(), vec!["Hei", "verden", "Les & skriv"]);
+
+        let joined = join_segments(&document.texts());
+        assert_eq!(joined, "⟦1⟧ Hei\n⟦2⟧ verden\n⟦3⟧ Les & skriv");
+        let translated = "⟦1⟧ Hello\n⟦2⟧ world\n⟦3⟧ Read & write";
+        document.set_texts(split_segments(translated, 3).unwrap());
+
+        assert_eq!(
+            document.to_html(),
+            "<ul><li><b>Hello</b> world</li>\n<li><a href=\"https://x.no?a=1&amp;b=2\">Read &amp; write</a></li></ul><style>li { color: red }</style>"
+        );
+        assert_eq!(document.plain_text(), "Hello world\nRead & write");
+    }
+
+    #[test]
+    fn rejects_translations_that_lost_markers() {
+        assert_eq!(split_segments("⟦1⟧ Hello ⟦3⟧ world", 3), None);
+        assert_eq!(split_segments("⟦2⟧ world ⟦1⟧ Hello", 2), None);
+        assert_eq!(split_segments("⟦1⟧ Hello ⟦2⟧ world ⟦3⟧ extra", 2), None);
+        assert_eq!(strip_markers("⟦1⟧ Hello\n⟦2⟧ world"), "Hello\nworld");
+    }
+}
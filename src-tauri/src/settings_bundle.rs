@@ -0,0 +1,336 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::config_layers;
+use crate::profiles::{self, Profile};
+
+// Bumped whenever the bundle layout changes in a way older readers cannot handle
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const SECRET_FIELDS: &[&str] = &["openai_api_key", "azure_api_key"];
+
+// Machine-specific settings that are never exported or imported. The OCR settings name
+// a program to run, which a shared file must not be able to choose
+const LOCAL_FIELDS: &[&str] = &["encryption", "active_profile", "ocr"];
+
+const MASKED_SECRET: &str = "********";
+
+/// Settings, prompt and profiles in a single file for sharing a setup
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingsBundle {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub includes_secrets: bool,
+    pub config: Map<String, Value>, // Includes the custom prompt
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingChange {
+    pub field: String,
+    pub current: Value,
+    pub incoming: Value, // API keys are masked
+}
+
+/// What importing a bundle changes, shown to the user before anything is written
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SettingsImportPreview {
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub includes_secrets: bool,
+    pub changes: Vec<SettingChange>,
+    pub profiles_added: Vec<String>,
+    pub profiles_replaced: Vec<String>,
+}
+
+fn settings_map(config: &Config) -> Result<Map<String, Value>> {
+    match serde_json::to_value(config_layers::saved_form(config)?)? {
+        Value::Object(mut fields) => {
+            for field in LOCAL_FIELDS {
+                fields.remove(*field);
+            }
+            Ok(fields)
+        }
+        _ => Err(anyhow::anyhow!("Settings are not a JSON object")),
+    }
+}
+
+/// Writes the bundle to `path`; API keys must be readable, i.e. the vault unlocked,
+/// when `include_secrets` is set
+pub fn export_settings(path: &Path, config: &Config, include_secrets: bool) -> Result<()> {
+    let mut fields = settings_map(config)?;
+    for field in SECRET_FIELDS {
+        let value = fields.get(*field).and_then(Value::as_str).unwrap_or("");
+        if !include_secrets {
+            fields.insert(field.to_string(), Value::from(""));
+        } else if crate::vault::is_encrypted(value) {
+            return Err(anyhow::anyhow!(
+                "Unlock encrypted storage to export API keys"
+            ));
+        }
+    }
+
+    let bundle = SettingsBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        includes_secrets: include_secrets,
+        config: fields,
+        profiles: profiles::export_profiles(include_secrets)?,
+    };
+    fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+    Ok(())
+}
+
+fn read_bundle(path: &Path) -> Result<SettingsBundle> {
+    let contents = fs::read_to_string(path)?;
+    let bundle: SettingsBundle = serde_json::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!("Not a GPTranslate settings bundle: {}", e))?;
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(anyhow::anyhow!(
+            "The bundle was created by a newer version of GPTranslate ({}), please update first",
+            bundle.app_version
+        ));
+    }
+    Ok(bundle)
+}
+
+// Builds the config that importing the bundle results in; local settings such as
+// encryption and OCR stay as they are, and so do API keys when the bundle has none. The Azure
+// key is dropped instead when the bundle points at another endpoint, since it would be
+// sent to a server it was not issued for
+fn imported_config(bundle: &SettingsBundle, current: &Config) -> Result<Config> {
+    let mut fields = settings_map(current)?;
+    for (key, value) in &bundle.config {
+        let is_local = LOCAL_FIELDS.contains(&key.as_str());
+        let is_skipped_secret = !bundle.includes_secrets && SECRET_FIELDS.contains(&key.as_str());
+        if !is_local && !is_skipped_secret {
+            fields.insert(key.clone(), value.clone());
+        }
+    }
+    let endpoint_changed =
+        fields.get("azure_endpoint").and_then(Value::as_str) != Some(&current.azure_endpoint);
+    if !bundle.includes_secrets && endpoint_changed {
+        fields.insert("azure_api_key".to_string(), Value::from(""));
+    }
+
+    let mut config = Config::from_settings(fields)?;
+    config.encryption = current.encryption.clone();
+    config.active_profile = current.active_profile.clone();
+    config.ocr = current.ocr.clone();
+    Ok(config)
+}
+
+fn display_value(field: &str, value: Option<&Value>) -> Value {
+    match value {
+        Some(Value::String(secret)) if SECRET_FIELDS.contains(&field) && !secret.is_empty() => {
+            Value::from(MASKED_SECRET)
+        }
+        Some(value) => value.clone(),
+        None => Value::Null,
+    }
+}
+
+fn build_preview(
+    bundle: &SettingsBundle,
+    current: &Config,
+    existing_profiles: &[String],
+) -> Result<SettingsImportPreview> {
+    let before = settings_map(current)?;
+    let after = settings_map(&imported_config(bundle, current)?)?;
+
+    let mut changes = Vec::new();
+    for (field, incoming) in &after {
+        let current = before.get(field);
+        if current != Some(incoming) {
+            changes.push(SettingChange {
+                field: field.clone(),
+                current: display_value(field, current),
+                incoming: display_value(field, Some(incoming)),
+            });
+        }
+    }
+
+    let (profiles_replaced, profiles_added) = bundle
+        .profiles
+        .iter()
+        .map(|profile| profile.name.clone())
+        .partition(|name| existing_profiles.contains(name));
+
+    Ok(SettingsImportPreview {
+        app_version: bundle.app_version.clone(),
+        created_at: bundle.created_at,
+        includes_secrets: bundle.includes_secrets,
+        changes,
+        profiles_added,
+        profiles_replaced,
+    })
+}
+
+fn existing_profile_names() -> Result<Vec<String>> {
+    Ok(profiles::list_profiles(None)?
+        .into_iter()
+        .map(|profile| profile.name)
+        .collect())
+}
+
+pub fn preview_import(path: &Path, current: &Config) -> Result<SettingsImportPreview> {
+    build_preview(&read_bundle(path)?, current, &existing_profile_names()?)
+}
+
+/// Imports the bundle's profiles and returns the resulting config for the caller to
+/// save and apply, together with the preview of what changed
+pub fn import_settings(path: &Path, current: &Config) -> Result<(Config, SettingsImportPreview)> {
+    let bundle = read_bundle(path)?;
+    let preview = build_preview(&bundle, current, &existing_profile_names()?)?;
+    let config = imported_config(&bundle, current)?;
+
+    profiles::import_profiles(bundle.profiles, bundle.includes_secrets)?;
+    Ok((config, preview))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current() -> Config {
+        Config {
+            api_provider: "azure_openai".to_string(),
+            openai_api_key: "sk-mine".to_string(),
+            azure_endpoint: "https://mine.openai.azure.com/".to_string(),
+            azure_api_key: "azure-mine".to_string(),
+            active_profile: Some("Work".to_string()),
+            ..Config::default()
+        }
+    }
+
+    fn bundle(includes_secrets: bool, changes: Value) -> SettingsBundle {
+        let mut config = settings_map(&Config::default()).unwrap();
+        config.insert(
+            "azure_endpoint".to_string(),
+            Value::from("https://mine.openai.azure.com/"),
+        );
+        if let Value::Object(changes) = changes {
+            config.extend(changes);
+        }
+        SettingsBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            app_version: "1.0.0".to_string(),
+            created_at: Utc::now(),
+            includes_secrets,
+            config,
+            profiles: vec![
+                Profile {
+                    name: "Work".to_string(),
+                    ..Profile::default()
+                },
+                Profile {
+                    name: "Home".to_string(),
+                    ..Profile::default()
+                },
+            ],
+        }
+    }
+
+    fn changed_fields(preview: &SettingsImportPreview) -> Vec<&str> {
+        preview
+            .changes
+            .iter()
+            .map(|change| change.field.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_local_settings_and_api_keys_without_secrets() {
+        let incoming = bundle(
+            false,
+            serde_json::json!({ "model": "gpt-4.1", "active_profile": "Other" }),
+        );
+        let config = imported_config(&incoming, &current()).unwrap();
+        assert_eq!(config.model, "gpt-4.1");
+        assert_eq!(config.api_provider, "openai");
+        assert_eq!(config.openai_api_key, "sk-mine");
+        assert_eq!(config.azure_api_key, "azure-mine");
+        assert_eq!(config.active_profile.as_deref(), Some("Work"));
+
+        let preview = build_preview(&incoming, &current(), &["Work".to_string()]).unwrap();
+        assert_eq!(changed_fields(&preview), vec!["api_provider", "model"]);
+        assert_eq!(preview.profiles_replaced, vec!["Work"]);
+        assert_eq!(preview.profiles_added, vec!["Home"]);
+    }
+
+    #[test]
+    fn never_takes_the_ocr_program_from_a_bundle() {
+        let mut current = current();
+        current.ocr.tesseract_path = "/usr/local/bin/tesseract".to_string();
+        current.ocr.tessdata_dir = "/usr/local/share/tessdata".to_string();
+        assert!(!settings_map(&current).unwrap().contains_key("ocr"));
+
+        let incoming = bundle(
+            true,
+            serde_json::json!({
+                "ocr": {
+                    "languages": "eng",
+                    "tesseract_path": "/tmp/payload",
+                    "tessdata_dir": "/tmp"
+                }
+            }),
+        );
+        let config = imported_config(&incoming, &current).unwrap();
+        assert_eq!(config.ocr.tesseract_path, "/usr/local/bin/tesseract");
+        assert_eq!(config.ocr.tessdata_dir, "/usr/local/share/tessdata");
+
+        let preview = build_preview(&incoming, &current, &[]).unwrap();
+        assert!(!changed_fields(&preview).contains(&"ocr"));
+    }
+
+    #[test]
+    fn drops_the_azure_key_when_the_endpoint_changes() {
+        let incoming = bundle(
+            false,
+            serde_json::json!({ "azure_endpoint": "https://theirs.openai.azure.com/" }),
+        );
+        let config = imported_config(&incoming, &current()).unwrap();
+        assert_eq!(config.azure_endpoint, "https://theirs.openai.azure.com/");
+        assert_eq!(config.azure_api_key, "");
+        assert_eq!(config.openai_api_key, "sk-mine");
+
+        let preview = build_preview(&incoming, &current(), &[]).unwrap();
+        let key_change = preview
+            .changes
+            .iter()
+            .find(|change| change.field == "azure_api_key")
+            .unwrap();
+        assert_eq!(key_change.current, Value::from(MASKED_SECRET));
+        assert_eq!(key_change.incoming, Value::from(""));
+    }
+
+    #[test]
+    fn imports_and_masks_secrets_from_the_bundle() {
+        let incoming = bundle(
+            true,
+            serde_json::json!({
+                "azure_endpoint": "https://theirs.openai.azure.com/",
+                "azure_api_key": "azure-theirs",
+                "openai_api_key": "sk-theirs"
+            }),
+        );
+        let config = imported_config(&incoming, &current()).unwrap();
+        assert_eq!(config.azure_api_key, "azure-theirs");
+        assert_eq!(config.openai_api_key, "sk-theirs");
+
+        let preview = build_preview(&incoming, &current(), &[]).unwrap();
+        for change in &preview.changes {
+            if SECRET_FIELDS.contains(&change.field.as_str()) {
+                assert_eq!(change.incoming, Value::from(MASKED_SECRET));
+            }
+        }
+    }
+}
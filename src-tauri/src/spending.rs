@@ -0,0 +1,427 @@
+use anyhow::Result;
+use chrono::{Duration, Local};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::paths;
+use crate::translation::TokenUsage;
+
+// Daily totals older than this are dropped from the ledger
+const DAILY_RETENTION_DAYS: i64 = 400;
+
+lazy_static! {
+    // Serializes read-modify-write cycles on spending.json, and holds the estimated cost
+    // of requests that were let through but have not recorded their cost yet
+    static ref LEDGER_LOCK: Mutex<f64> = Mutex::new(0.0);
+}
+
+/// Price in USD per million tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+pub fn default_model_prices() -> BTreeMap<String, ModelPrice> {
+    let price = |input_per_million, output_per_million| ModelPrice {
+        input_per_million,
+        output_per_million,
+    };
+    BTreeMap::from([
+        ("gpt-4.1".to_string(), price(2.00, 8.00)),
+        ("gpt-4.1-mini".to_string(), price(0.40, 1.60)),
+        ("gpt-4.1-nano".to_string(), price(0.10, 0.40)),
+        ("gpt-4o".to_string(), price(2.50, 10.00)),
+        ("gpt-4o-mini".to_string(), price(0.15, 0.60)),
+    ])
+}
+
+/// Spending limits in USD; a missing value means no limit
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BudgetLimits {
+    pub daily_soft_limit: Option<f64>,
+    pub daily_hard_limit: Option<f64>,
+    pub monthly_soft_limit: Option<f64>,
+    pub monthly_hard_limit: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Daily,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Monthly => "monthly",
+        }
+    }
+}
+
+/// Returned when a hard limit blocks a request
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BudgetExceeded {
+    pub period: BudgetPeriod,
+    pub limit: f64,
+    pub spent: f64,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The {} spending limit of ${:.2} has been reached (${:.2} spent), translation requests are blocked until the limit is raised or the period ends",
+            self.period.as_str(),
+            self.limit,
+            self.spent
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SpendingLedger {
+    #[serde(default)]
+    daily: BTreeMap<String, f64>, // Keyed by local date, "YYYY-MM-DD"
+    #[serde(default)]
+    monthly: BTreeMap<String, f64>, // Keyed by local month, "YYYY-MM"
+    #[serde(default)]
+    warned: BTreeSet<String>, // Periods a soft limit notification was already shown for
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpendingSummary {
+    pub today: f64,
+    pub this_month: f64,
+    pub daily: BTreeMap<String, f64>,
+    pub monthly: BTreeMap<String, f64>,
+    pub limits: BudgetLimits,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CostEstimate {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost: Option<f64>, // None when the model has no configured price
+    pub spent_today: f64,
+    pub spent_this_month: f64,
+    pub exceeds_hard_limit: bool,
+}
+
+fn get_ledger_path() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("spending.json"))
+}
+
+fn today_key() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn month_key() -> String {
+    Local::now().format("%Y-%m").to_string()
+}
+
+fn load_ledger() -> Result<SpendingLedger> {
+    let path = get_ledger_path()?;
+    if !path.exists() {
+        return Ok(SpendingLedger::default());
+    }
+    let contents = fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn save_ledger(ledger: &SpendingLedger) -> Result<()> {
+    let path = get_ledger_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(ledger)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Looks up the price for a model, falling back to the longest configured prefix
+/// so dated snapshots such as "gpt-4.1-nano-2025-04-14" use their family's price
+pub fn price_for_model<'a>(
+    prices: &'a BTreeMap<String, ModelPrice>,
+    model: &str,
+) -> Option<&'a ModelPrice> {
+    prices.get(model).or_else(|| {
+        prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| price)
+    })
+}
+
+pub fn cost_of(price: &ModelPrice, usage: &TokenUsage) -> f64 {
+    (usage.prompt_tokens as f64 * price.input_per_million
+        + usage.completion_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
+// Rough token count for pre-flight estimates, about four characters per token
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+fn spent(ledger: &SpendingLedger) -> (f64, f64) {
+    (
+        ledger.daily.get(&today_key()).copied().unwrap_or(0.0),
+        ledger.monthly.get(&month_key()).copied().unwrap_or(0.0),
+    )
+}
+
+fn exceeded_hard_limit(limits: &BudgetLimits, today: f64, month: f64) -> Option<BudgetExceeded> {
+    if let Some(limit) = limits.daily_hard_limit.filter(|limit| today >= *limit) {
+        return Some(BudgetExceeded {
+            period: BudgetPeriod::Daily,
+            limit,
+            spent: today,
+        });
+    }
+    if let Some(limit) = limits.monthly_hard_limit.filter(|limit| month >= *limit) {
+        return Some(BudgetExceeded {
+            period: BudgetPeriod::Monthly,
+            limit,
+            spent: month,
+        });
+    }
+    None
+}
+
+/// The estimated cost of a request in flight, held against the hard limits until the
+/// real cost is recorded or the request fails and this is dropped
+pub struct Reservation {
+    amount: f64,
+}
+
+impl Reservation {
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.amount > 0.0 {
+            *LEDGER_LOCK.lock().unwrap() -= self.amount;
+        }
+    }
+}
+
+// Hard limits count what was spent, the other requests in flight and this one, so
+// requests sent at the same time cannot all pass on the same remaining budget
+fn exceeded_with_pending(
+    limits: &BudgetLimits,
+    (today, month): (f64, f64),
+    pending: f64,
+) -> Option<BudgetExceeded> {
+    exceeded_hard_limit(limits, today + pending, month + pending).map(|mut exceeded| {
+        exceeded.spent = match exceeded.period {
+            BudgetPeriod::Daily => today,
+            BudgetPeriod::Monthly => month,
+        };
+        exceeded
+    })
+}
+
+/// Reserves the estimated cost of a request before it is sent. Fails with
+/// `BudgetExceeded` when it would go over a hard limit, and when a hard limit is set
+/// but `model` has no price to estimate with
+pub fn reserve(limits: &BudgetLimits, model: &str, estimate: Option<f64>) -> Result<Reservation> {
+    let has_hard_limit = limits.daily_hard_limit.is_some() || limits.monthly_hard_limit.is_some();
+    let estimate = match estimate {
+        Some(estimate) => estimate,
+        None if has_hard_limit => {
+            return Err(anyhow::anyhow!(
+                "No price is configured for {}, so the hard spending limit cannot be applied. Add a price for it or remove the hard limit",
+                model
+            ));
+        }
+        None => 0.0,
+    };
+
+    let mut reserved = LEDGER_LOCK.lock().unwrap();
+    if has_hard_limit {
+        let spent = spent(&load_ledger()?);
+        if let Some(exceeded) = exceeded_with_pending(limits, spent, *reserved + estimate) {
+            return Err(exceeded.into());
+        }
+    }
+    *reserved += estimate;
+    Ok(Reservation { amount: estimate })
+}
+
+/// Replaces the reservation with the real cost of the request and returns a warning
+/// message the first time a soft limit is crossed in the current period
+pub fn record_spend(
+    limits: &BudgetLimits,
+    mut reservation: Reservation,
+    cost: f64,
+) -> Result<Option<String>> {
+    let mut reserved = LEDGER_LOCK.lock().unwrap();
+    *reserved -= reservation.amount;
+    reservation.amount = 0.0;
+    let mut ledger = load_ledger()?;
+    let warning = add_spend(&mut ledger, limits, cost);
+    save_ledger(&ledger)?;
+    Ok(warning)
+}
+
+fn add_spend(ledger: &mut SpendingLedger, limits: &BudgetLimits, cost: f64) -> Option<String> {
+    let (day, month) = (today_key(), month_key());
+    *ledger.daily.entry(day.clone()).or_default() += cost;
+    *ledger.monthly.entry(month.clone()).or_default() += cost;
+
+    let cutoff = (Local::now() - Duration::days(DAILY_RETENTION_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+    ledger.daily.retain(|key, _| *key >= cutoff);
+    ledger
+        .warned
+        .retain(|key| key.ends_with(&day) || key.ends_with(&month));
+
+    let (today, this_month) = spent(ledger);
+    let mut warning = None;
+    for (period, limit, spent, period_key) in [
+        (BudgetPeriod::Daily, limits.daily_soft_limit, today, &day),
+        (
+            BudgetPeriod::Monthly,
+            limits.monthly_soft_limit,
+            this_month,
+            &month,
+        ),
+    ] {
+        let Some(limit) = limit.filter(|limit| spent >= *limit) else {
+            continue;
+        };
+        if ledger
+            .warned
+            .insert(format!("{}:{}", period.as_str(), period_key))
+        {
+            warning = Some(format!(
+                "You have spent ${:.2} {}, above your soft limit of ${:.2}",
+                spent,
+                if period == BudgetPeriod::Daily {
+                    "today"
+                } else {
+                    "this month"
+                },
+                limit
+            ));
+        }
+    }
+    warning
+}
+
+pub fn get_spending_summary(limits: &BudgetLimits) -> Result<SpendingSummary> {
+    let _guard = LEDGER_LOCK.lock().unwrap();
+    let ledger = load_ledger()?;
+    let (today, this_month) = spent(&ledger);
+    Ok(SpendingSummary {
+        today,
+        this_month,
+        daily: ledger.daily,
+        monthly: ledger.monthly,
+        limits: limits.clone(),
+    })
+}
+
+pub fn estimate_cost(config: &Config, model: &str, usage: TokenUsage) -> Result<CostEstimate> {
+    let estimated_cost = price_for_model(&config.model_prices, model).map(|p| cost_of(p, &usage));
+
+    let reserved = LEDGER_LOCK.lock().unwrap();
+    let (today, month) = spent(&load_ledger()?);
+    let extra = *reserved + estimated_cost.unwrap_or(0.0);
+
+    Ok(CostEstimate {
+        model: model.to_string(),
+        input_tokens: usage.prompt_tokens,
+        output_tokens: usage.completion_tokens,
+        estimated_cost,
+        spent_today: today,
+        spent_this_month: month,
+        exceeds_hard_limit: exceeded_hard_limit(&config.budget, today + extra, month + extra)
+            .is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn prices_dated_snapshots_by_their_longest_prefix() {
+        let prices = default_model_prices();
+        let price = |model| price_for_model(&prices, model).map(|p| p.input_per_million);
+
+        assert_eq!(price("gpt-4.1"), Some(2.00));
+        assert_eq!(price("gpt-4.1-nano-2025-04-14"), Some(0.10));
+        assert_eq!(price("gpt-4o-2024-08-06"), Some(2.50));
+        assert_eq!(price("gpt-4o-mini-2024-07-18"), Some(0.15));
+        assert_eq!(price("o3-mini"), None);
+
+        let mini = &prices["gpt-4.1-mini"];
+        let cost = cost_of(mini, &usage(1_000_000, 500_000));
+        assert!((cost - 1.20).abs() < 1e-9);
+        assert_eq!(cost_of(mini, &usage(0, 0)), 0.0);
+    }
+
+    #[test]
+    fn warns_once_per_period_when_a_soft_limit_is_crossed() {
+        let limits = BudgetLimits {
+            daily_soft_limit: Some(1.0),
+            monthly_soft_limit: Some(10.0),
+            ..Default::default()
+        };
+        let mut ledger = SpendingLedger::default();
+
+        assert_eq!(add_spend(&mut ledger, &limits, 0.6), None);
+        let warning = add_spend(&mut ledger, &limits, 0.6).unwrap();
+        assert!(warning.contains("today"), "{}", warning);
+        assert_eq!(add_spend(&mut ledger, &limits, 0.6), None);
+
+        // The monthly limit warns on its own, once
+        let warning = add_spend(&mut ledger, &limits, 9.0).unwrap();
+        assert!(warning.contains("this month"), "{}", warning);
+        assert_eq!(add_spend(&mut ledger, &limits, 1.0), None);
+
+        let (today, month) = spent(&ledger);
+        assert!((today - 11.8).abs() < 1e-9);
+        assert!((month - 11.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hard_limits_count_requests_in_flight() {
+        let limits = BudgetLimits {
+            daily_hard_limit: Some(1.0),
+            ..Default::default()
+        };
+        // Each request fits on its own, but not next to one already in flight
+        assert!(exceeded_with_pending(&limits, (0.5, 0.5), 0.3).is_none());
+        let exceeded = exceeded_with_pending(&limits, (0.5, 0.5), 0.3 + 0.3).unwrap();
+        assert_eq!(exceeded.period, BudgetPeriod::Daily);
+        assert_eq!(exceeded.spent, 0.5);
+
+        assert!(reserve(&limits, "my-azure-deployment", None).is_err());
+        let unlimited = reserve(&BudgetLimits::default(), "my-azure-deployment", None).unwrap();
+        assert_eq!(unlimited.amount(), 0.0);
+    }
+}
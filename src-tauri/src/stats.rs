@@ -0,0 +1,405 @@
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::history::{load_history, TranslationEntry};
+use crate::history_io::HistoryFilter;
+use crate::paths;
+
+// Number of entries returned in the most-translated phrases list
+const TOP_PHRASES_LIMIT: usize = 10;
+
+// Daily usage older than this is dropped from the ledger
+const USAGE_RETENTION_DAYS: i64 = 400;
+
+lazy_static! {
+    // Serializes read-modify-write cycles on usage.json
+    static ref USAGE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguagePairCount {
+    pub source_language: String,
+    pub target_language: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub count: usize,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseCount {
+    pub text: String,
+    pub count: usize,
+}
+
+/// Aggregates over the usage ledger, which counts every translation added to history.
+/// Only `top_phrases` needs the texts and comes from the recent history entries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStats {
+    pub total_translations: usize,
+    pub total_characters: usize,
+    pub total_tokens: u64,
+    pub average_latency_ms: Option<f64>, // Only translations recorded with metadata carry a latency
+    pub by_language_pair: Vec<LanguagePairCount>,
+    pub by_day: BTreeMap<String, usize>,  // Keyed by "YYYY-MM-DD"
+    pub by_week: BTreeMap<String, usize>, // Keyed by ISO week, e.g. "2025-W07"
+    pub by_model: Vec<ModelUsage>,
+    pub top_phrases: Vec<PhraseCount>,
+}
+
+// Counters for one language pair and model on one day
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct UsageBucket {
+    source_language: String,
+    target_language: String,
+    provider: String, // Empty for translations recorded without metadata
+    model: String,
+    translations: usize,
+    characters: usize,
+    tokens: u64,
+    latency_ms: u64, // Summed over the translations in `timed`
+    timed: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct UsageLedger {
+    #[serde(default)]
+    days: BTreeMap<String, Vec<UsageBucket>>, // Keyed by UTC date, "YYYY-MM-DD"
+}
+
+fn get_ledger_path() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("usage.json"))
+}
+
+fn load_ledger() -> Result<Option<UsageLedger>> {
+    let path = get_ledger_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+fn save_ledger(ledger: &UsageLedger) -> Result<()> {
+    let path = get_ledger_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(ledger)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+fn add_usage(ledger: &mut UsageLedger, entry: &TranslationEntry) {
+    let (provider, model) = entry
+        .metadata
+        .as_ref()
+        .map(|metadata| (metadata.provider.clone(), metadata.model.clone()))
+        .unwrap_or_default();
+    let buckets = ledger
+        .days
+        .entry(entry.timestamp.date_naive().to_string())
+        .or_default();
+    let index = buckets
+        .iter()
+        .position(|bucket| {
+            bucket.source_language == entry.detected_language
+                && bucket.target_language == entry.target_language
+                && bucket.provider == provider
+                && bucket.model == model
+        })
+        .unwrap_or_else(|| {
+            buckets.push(UsageBucket {
+                source_language: entry.detected_language.clone(),
+                target_language: entry.target_language.clone(),
+                provider,
+                model,
+                ..Default::default()
+            });
+            buckets.len() - 1
+        });
+
+    let bucket = &mut buckets[index];
+    bucket.translations += 1;
+    bucket.characters += entry.original_text.chars().count();
+    if let Some(metadata) = &entry.metadata {
+        bucket.tokens += metadata
+            .usage
+            .as_ref()
+            .map_or(0, |usage| usage.total_tokens);
+        bucket.latency_ms += metadata.latency_ms;
+        bucket.timed += 1;
+    }
+}
+
+// The ledger starts out with whatever history still holds, so upgrading keeps the
+// statistics that were shown before
+fn seeded_ledger(history: &[TranslationEntry]) -> UsageLedger {
+    let mut ledger = UsageLedger::default();
+    for entry in history {
+        add_usage(&mut ledger, entry);
+    }
+    ledger
+}
+
+/// Counts a translation that was just added to history. `history` seeds the ledger
+/// when it does not exist yet, and already holds `entry`
+pub fn record_usage(entry: &TranslationEntry, history: &[TranslationEntry]) -> Result<()> {
+    let _guard = USAGE_LOCK.lock().unwrap();
+    let mut ledger = match load_ledger()? {
+        Some(mut ledger) => {
+            add_usage(&mut ledger, entry);
+            ledger
+        }
+        None => seeded_ledger(history),
+    };
+    let cutoff = (Utc::now() - Duration::days(USAGE_RETENTION_DAYS))
+        .date_naive()
+        .to_string();
+    ledger.days.retain(|day, _| *day >= cutoff);
+    save_ledger(&ledger)
+}
+
+pub async fn get_usage_stats(filter: &HistoryFilter) -> Result<UsageStats> {
+    let history = load_history().await?;
+    let ledger = {
+        let _guard = USAGE_LOCK.lock().unwrap();
+        load_ledger()?
+    }
+    .unwrap_or_else(|| seeded_ledger(&history.entries));
+    let entries: Vec<&TranslationEntry> = history
+        .entries
+        .iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    Ok(compute_usage_stats(&ledger, filter, &entries))
+}
+
+// Phrases are grouped case-insensitively with whitespace collapsed
+fn normalize_phrase(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn top_phrases(entries: &[&TranslationEntry]) -> Vec<PhraseCount> {
+    let mut phrases: HashMap<String, PhraseCount> = HashMap::new();
+    for entry in entries {
+        let phrase = normalize_phrase(&entry.original_text);
+        if !phrase.is_empty() {
+            phrases
+                .entry(phrase)
+                .or_insert_with(|| PhraseCount {
+                    text: entry.original_text.trim().to_string(),
+                    count: 0,
+                })
+                .count += 1;
+        }
+    }
+
+    let mut top_phrases: Vec<PhraseCount> = phrases.into_values().collect();
+    top_phrases.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+    top_phrases.truncate(TOP_PHRASES_LIMIT);
+    top_phrases
+}
+
+fn compute_usage_stats(
+    ledger: &UsageLedger,
+    filter: &HistoryFilter,
+    phrase_entries: &[&TranslationEntry],
+) -> UsageStats {
+    let mut stats = UsageStats::default();
+    let mut pairs: HashMap<(String, String), usize> = HashMap::new();
+    let mut models: HashMap<(String, String), ModelUsage> = HashMap::new();
+    let mut latency_total: u64 = 0;
+    let mut latency_count: u64 = 0;
+
+    for (day_key, buckets) in &ledger.days {
+        let Ok(day) = day_key.parse::<NaiveDate>() else {
+            continue;
+        };
+        if !filter.matches_day(day) {
+            continue;
+        }
+        for bucket in buckets {
+            if !filter.matches_languages(&bucket.source_language, &bucket.target_language) {
+                continue;
+            }
+            stats.total_translations += bucket.translations;
+            stats.total_characters += bucket.characters;
+            stats.total_tokens += bucket.tokens;
+            latency_total += bucket.latency_ms;
+            latency_count += bucket.timed;
+
+            *stats.by_day.entry(day_key.clone()).or_default() += bucket.translations;
+            let week = day.iso_week();
+            *stats
+                .by_week
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_default() += bucket.translations;
+
+            *pairs
+                .entry((
+                    bucket.source_language.clone(),
+                    bucket.target_language.clone(),
+                ))
+                .or_default() += bucket.translations;
+
+            if bucket.timed > 0 {
+                let usage = models
+                    .entry((bucket.provider.clone(), bucket.model.clone()))
+                    .or_insert_with(|| ModelUsage {
+                        provider: bucket.provider.clone(),
+                        model: bucket.model.clone(),
+                        count: 0,
+                        total_tokens: 0,
+                    });
+                usage.count += bucket.timed as usize;
+                usage.total_tokens += bucket.tokens;
+            }
+        }
+    }
+
+    if latency_count > 0 {
+        stats.average_latency_ms = Some(latency_total as f64 / latency_count as f64);
+    }
+
+    stats.by_language_pair = pairs
+        .into_iter()
+        .map(
+            |((source_language, target_language), count)| LanguagePairCount {
+                source_language,
+                target_language,
+                count,
+            },
+        )
+        .collect();
+    stats.by_language_pair.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.source_language.cmp(&b.source_language))
+            .then_with(|| a.target_language.cmp(&b.target_language))
+    });
+
+    stats.by_model = models.into_values().collect();
+    stats.by_model.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.provider.cmp(&b.provider))
+            .then_with(|| a.model.cmp(&b.model))
+    });
+
+    stats.top_phrases = top_phrases(phrase_entries);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::{CaptureSource, TokenUsage, TranslationMetadata};
+    use chrono::TimeZone;
+
+    fn entry(text: &str, day: u32, model: Option<(&str, u64, u64)>) -> TranslationEntry {
+        TranslationEntry {
+            id: text.to_string(),
+            original_text: text.to_string(),
+            translated_text: text.to_uppercase(),
+            detected_language: "Norwegian".to_string(),
+            target_language: "English".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 2, day, 12, 0, 0).unwrap(),
+            metadata: model.map(|(model, tokens, latency_ms)| TranslationMetadata {
+                provider: "openai".to_string(),
+                model: model.to_string(),
+                prompt_hash: String::new(),
+                latency_ms,
+                usage: Some(TokenUsage {
+                    total_tokens: tokens,
+                    ..Default::default()
+                }),
+                used_alternative_target: false,
+                used_json_fallback: false,
+                capture_source: CaptureSource::Manual,
+                cost_usd: None,
+            }),
+            parent_id: None,
+            revisions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aggregates_entries_by_period_model_and_phrase() {
+        let entries = [
+            entry("Hei  verden", 10, Some(("gpt-4.1", 30, 200))),
+            entry("hei verden", 16, Some(("gpt-4.1", 50, 400))),
+            entry("Takk", 17, Some(("gpt-4o-mini", 20, 300))),
+            entry("Ha det", 17, None),
+        ];
+        let refs: Vec<&TranslationEntry> = entries.iter().collect();
+        let stats = compute_usage_stats(&seeded_ledger(&entries), &HistoryFilter::default(), &refs);
+
+        assert_eq!(stats.total_translations, 4);
+        assert_eq!(stats.total_characters, 11 + 10 + 4 + 6);
+        assert_eq!(stats.total_tokens, 100);
+        // Entries without metadata have no latency and do not count towards the mean
+        assert_eq!(stats.average_latency_ms, Some(300.0));
+
+        assert_eq!(stats.by_day["2025-02-17"], 2);
+        assert_eq!(stats.by_week["2025-W07"], 2);
+        assert_eq!(stats.by_week["2025-W08"], 2);
+        assert_eq!(stats.by_language_pair[0].count, 4);
+
+        assert_eq!(stats.by_model[0].model, "gpt-4.1");
+        assert_eq!(stats.by_model[0].count, 2);
+        assert_eq!(stats.by_model[0].total_tokens, 80);
+        assert_eq!(stats.by_model[1].model, "gpt-4o-mini");
+
+        assert_eq!(stats.top_phrases[0].text, "Hei  verden");
+        assert_eq!(stats.top_phrases[0].count, 2);
+        assert_eq!(stats.top_phrases.len(), 3);
+    }
+
+    #[test]
+    fn counts_more_translations_than_history_keeps() {
+        let mut ledger = UsageLedger::default();
+        for i in 0..250 {
+            add_usage(
+                &mut ledger,
+                &entry(
+                    &format!("tekst {}", i),
+                    1 + i % 28,
+                    Some(("gpt-4.1", 10, 100)),
+                ),
+            );
+        }
+        let mut swedish = entry("Tack", 20, None);
+        swedish.detected_language = "Swedish".to_string();
+        add_usage(&mut ledger, &swedish);
+
+        let stats = compute_usage_stats(&ledger, &HistoryFilter::default(), &[]);
+        assert_eq!(stats.total_translations, 251);
+        assert_eq!(stats.total_tokens, 2500);
+        assert_eq!(stats.by_model[0].count, 250);
+        // One bucket per day, language pair and model
+        assert_eq!(ledger.days["2025-02-20"].len(), 2);
+
+        let filter = HistoryFilter {
+            from: Some(Utc.with_ymd_and_hms(2025, 2, 20, 0, 0, 0).unwrap()),
+            to: Some(Utc.with_ymd_and_hms(2025, 2, 20, 23, 0, 0).unwrap()),
+            source_language: Some("swedish".to_string()),
+            ..Default::default()
+        };
+        let stats = compute_usage_stats(&ledger, &filter, &[]);
+        assert_eq!(stats.total_translations, 1);
+        assert_eq!(stats.average_latency_ms, None);
+    }
+}
@@ -1,12 +1,26 @@
 use crate::config::Config;
+use crate::spending::{self, BudgetExceeded, CostEstimate};
 use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use lazy_static::lazy_static;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+// Upper bound on the length of the model's reply
+const MAX_OUTPUT_TOKENS: u64 = 800;
+
+/// Longest side images are scaled down to before they are sent, larger ones are scaled
+/// down by the API anyway
+pub const MAX_IMAGE_SIDE: usize = 2048;
+// The image APIs refuse larger uploads
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+// Input tokens a high-detail image of MAX_IMAGE_SIDE costs at most, for budget checks
+const IMAGE_TOKEN_ESTIMATE: u64 = 1600;
+
 lazy_static! {
     static ref IN_FLIGHT_REQUESTS: Arc<Mutex<HashMap<String, std::time::Instant>>> =
         Arc::new(Mutex::new(HashMap::new()));
@@ -16,6 +30,82 @@ lazy_static! {
 pub struct TranslationResult {
     pub detected_language: String,
     pub translated_text: String,
+    #[serde(default)]
+    pub metadata: TranslationMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_warning: Option<String>, // Set when this request pushed spending over a soft limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_text: Option<String>, // The text the model read, for image translations
+}
+
+/// Where the text for a translation came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    Hotkey,
+    #[default]
+    Manual,
+    History, // Re-translation of an existing history entry
+    Watcher, // Copied in another application while clipboard watching is on
+    Image,   // Read from a clipboard image
+}
+
+/// Per-request replacements for config values, used when re-translating from history
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranslationOverrides {
+    pub target_language: Option<String>,
+    pub model: Option<String>,
+    pub prompt: Option<String>,
+}
+
+impl TranslationOverrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(target_language) = &self.target_language {
+            config.target_language = target_language.clone();
+        }
+        if let Some(model) = &self.model {
+            // Azure serves the model through the deployment, OpenAI takes it per request
+            if config.api_provider == "azure_openai" {
+                config.azure_deployment_name = model.clone();
+            } else {
+                config.model = model.clone();
+            }
+        }
+        if let Some(prompt) = &self.prompt {
+            config.custom_prompt = prompt.clone();
+        }
+    }
+}
+
+/// Token counts as reported in the `usage` block of a chat completion response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+/// Details about how a translation was produced, kept for auditing model changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranslationMetadata {
+    pub provider: String,
+    pub model: String,
+    pub prompt_hash: String,
+    pub latency_ms: u64,
+    pub usage: Option<TokenUsage>,
+    pub used_alternative_target: bool,
+    pub used_json_fallback: bool,
+    pub capture_source: CaptureSource,
+    #[serde(default)]
+    pub cost_usd: Option<f64>, // None when usage was not reported or the model has no price
+}
+
+// Identifies the system prompt in metadata without storing it
+fn prompt_hash(system_prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(system_prompt.as_bytes()))
 }
 
 pub struct TranslationService {
@@ -36,6 +126,12 @@ impl TranslationService {
     }
 
     pub async fn detect_and_translate(&self, text: &str) -> Result<TranslationResult> {
+        // Refuse to call the API when the request could go over a hard spending limit
+        let reservation = self.reserve_budget(
+            spending::estimate_tokens(&self.build_system_prompt())
+                + spending::estimate_tokens(text),
+        )?;
+
         // Create a more unique request key that includes current timestamp to prevent issues
         // with legitimate duplicate requests (e.g., user retrying the same text)
         let request_key = format!(
@@ -68,7 +164,10 @@ impl TranslationService {
         }
 
         let result = match self.perform_translation(text).await {
-            Ok(response) => Ok(response),
+            Ok(mut response) => {
+                self.record_cost(&mut response, reservation);
+                Ok(response)
+            }
             Err(e) => Err(e),
         };
 
@@ -79,6 +178,59 @@ impl TranslationService {
 
         result
     }
+    /// Translates the text in a PNG image, for models that accept images
+    pub async fn translate_image(&self, png: &[u8]) -> Result<TranslationResult> {
+        let reservation = self.reserve_budget(
+            spending::estimate_tokens(&self.build_system_prompt()) + IMAGE_TOKEN_ESTIMATE,
+        )?;
+        if png.len() > MAX_IMAGE_BYTES {
+            return Err(anyhow::anyhow!(
+                "The image is too large to send ({} MB, at most {} MB)",
+                png.len() / (1024 * 1024),
+                MAX_IMAGE_BYTES / (1024 * 1024)
+            ));
+        }
+
+        let image_url = format!("data:image/png;base64,{}", BASE64.encode(png));
+        let user_content = json!([
+            {
+                "type": "text",
+                "text": "Translate the text in this image. Also return the text as written in the image in a 'source_text' field."
+            },
+            {
+                "type": "image_url",
+                "image_url": { "url": image_url }
+            }
+        ]);
+        let mut response = self.request_translation(user_content).await?;
+        self.record_cost(&mut response, reservation);
+        Ok(response)
+    }
+
+    // Prices the request with the full reply length, the most it can cost
+    fn reserve_budget(&self, input_tokens: u64) -> Result<spending::Reservation> {
+        let usage = TokenUsage {
+            prompt_tokens: input_tokens,
+            completion_tokens: MAX_OUTPUT_TOKENS,
+            total_tokens: input_tokens + MAX_OUTPUT_TOKENS,
+        };
+        let estimate = spending::price_for_model(&self.config.model_prices, self.model_name())
+            .map(|price| spending::cost_of(price, &usage));
+        spending::reserve(&self.config.budget, self.model_name(), estimate)
+    }
+
+    // A reply without usage is counted at the reserved estimate
+    fn record_cost(&self, response: &mut TranslationResult, reservation: spending::Reservation) {
+        let cost = response.metadata.cost_usd.unwrap_or(reservation.amount());
+        if cost <= 0.0 {
+            return;
+        }
+        match spending::record_spend(&self.config.budget, reservation, cost) {
+            Ok(warning) => response.budget_warning = warning,
+            Err(e) => log::error!("Failed to record translation cost: {}", e),
+        }
+    }
+
     async fn perform_translation(&self, text: &str) -> Result<TranslationResult> {
         // Improve text cleaning to preserve paragraph structure
         // Instead of filtering out empty lines, preserve them as paragraph breaks
@@ -91,14 +243,14 @@ impl TranslationService {
         log::info!("Cleaned text for translation: {}", cleaned_text);
 
         let user_prompt = format!("Text to translate: \"{}\"", cleaned_text);
+        self.request_translation(json!(user_prompt)).await
+    }
 
-        // Create a smart prompt that handles the alternative language logic
-        let smart_prompt = format!(
-            "{}\n\n# Alternative Language Logic\n- Primary target language: {}\n- Alternative target language: {}\n- If the detected language matches the primary target language, translate to the alternative target language instead.\n- If the detected language is different from the primary target language, translate to the primary target language.",
-            self.config.custom_prompt,
-            self.config.target_language,
-            self.config.alternative_target_language
-        );
+    // Sends the system prompt with `user_content`, plain text or content parts, and
+    // parses the JSON reply
+    async fn request_translation(&self, user_content: Value) -> Result<TranslationResult> {
+        let system_prompt = self.build_system_prompt();
+        let prompt_hash = prompt_hash(&system_prompt);
 
         log::info!("Using smart prompt with alternative language logic");
         let mut request_body = json!({
@@ -108,16 +260,16 @@ impl TranslationService {
                     "content": [
                         {
                             "type": "text",
-                            "text": format!("{}\n\nAlways respond with valid JSON containing 'detected_language' and 'translated_text' fields. Make sure to properly escape newlines in the translated_text field.", smart_prompt)
+                            "text": system_prompt
                         }
                     ]
                 },
                 {
                     "role": "user",
-                    "content": user_prompt
+                    "content": user_content
                 }
             ],
-            "max_tokens": 800,
+            "max_tokens": MAX_OUTPUT_TOKENS,
             "temperature": 0.3
         });
         if self.config.api_provider == "openai" {
@@ -136,11 +288,30 @@ impl TranslationService {
             }
         }
 
+        let started = std::time::Instant::now();
         let response = if self.config.api_provider == "azure_openai" {
             self.call_azure_openai(request_body).await?
         } else {
             self.call_openai(request_body).await?
         };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        self.parse_completion(&response, prompt_hash, latency_ms)
+    }
+
+    // Reads the translation and the request's metadata out of a chat completion response
+    fn parse_completion(
+        &self,
+        response: &Value,
+        prompt_hash: String,
+        latency_ms: u64,
+    ) -> Result<TranslationResult> {
+        let usage = response
+            .get("usage")
+            .and_then(|usage| serde_json::from_value::<TokenUsage>(usage.clone()).ok());
+        let cost_usd = usage.as_ref().and_then(|usage| {
+            spending::price_for_model(&self.config.model_prices, self.model_name())
+                .map(|price| spending::cost_of(price, usage))
+        });
         let content = response["choices"][0]["message"]["content"]
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("No content in response"))?;
@@ -160,6 +331,7 @@ impl TranslationService {
         }
 
         // Try to parse as JSON, but handle cases where the AI might have returned plain text
+        let mut used_json_fallback = false;
         let parsed: Value = match serde_json::from_str(&cleaned_content) {
             Ok(json) => {
                 log::info!("Successfully parsed JSON response");
@@ -167,6 +339,7 @@ impl TranslationService {
             }
             Err(parse_error) => {
                 log::warn!("Failed to parse as JSON: {}", parse_error);
+                used_json_fallback = true;
 
                 // Try to find and extract valid JSON from the response
                 if let Some(start_idx) = cleaned_content.find('{') {
@@ -244,6 +417,10 @@ impl TranslationService {
                 }
             }
         };
+        let source_text = parsed["source_text"]
+            .as_str()
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string);
         log::info!("Detected language: {}", detected_language);
         log::info!("Target language: {}", self.config.target_language);
         log::info!(
@@ -252,15 +429,16 @@ impl TranslationService {
         );
 
         // Log if alternative language logic should have been applied
-        if detected_language
-            .to_lowercase()
-            .contains(&self.config.target_language.to_lowercase())
-            || self
-                .config
-                .target_language
+        let used_alternative_target = !detected_language.is_empty()
+            && (detected_language
                 .to_lowercase()
-                .contains(&detected_language.to_lowercase())
-        {
+                .contains(&self.config.target_language.to_lowercase())
+                || self
+                    .config
+                    .target_language
+                    .to_lowercase()
+                    .contains(&detected_language.to_lowercase()));
+        if used_alternative_target {
             log::info!(
                 "Alternative language logic should apply - detected '{}' matches target '{}'",
                 detected_language,
@@ -280,9 +458,64 @@ impl TranslationService {
         Ok(TranslationResult {
             detected_language,
             translated_text,
+            metadata: TranslationMetadata {
+                provider: self.config.api_provider.clone(),
+                model: self.model_name().to_string(),
+                prompt_hash,
+                latency_ms,
+                usage,
+                used_alternative_target,
+                used_json_fallback,
+                capture_source: CaptureSource::default(),
+                cost_usd,
+            },
+            budget_warning: None,
+            source_text,
         })
     }
 
+    fn build_system_prompt(&self) -> String {
+        // Create a smart prompt that handles the alternative language logic
+        let smart_prompt = format!(
+            "{}\n\n# Alternative Language Logic\n- Primary target language: {}\n- Alternative target language: {}\n- If the detected language matches the primary target language, translate to the alternative target language instead.\n- If the detected language is different from the primary target language, translate to the primary target language.",
+            self.config.custom_prompt,
+            self.config.target_language,
+            self.config.alternative_target_language
+        );
+
+        // Formatted text is sent as segments marked ⟦1⟧, ⟦2⟧, ... by rich_text::join_segments
+        format!("{}\n\n# Marked Segments\n- If the text contains numbered markers such as ⟦1⟧ and ⟦2⟧, keep every marker exactly as written and in the same order, each in front of the translation of the text that follows it.\n- Never translate, renumber, merge or remove the markers.\n\nAlways respond with valid JSON containing 'detected_language' and 'translated_text' fields. Make sure to properly escape newlines in the translated_text field.", smart_prompt)
+    }
+
+    /// Estimates tokens and cost of translating `text` without calling the API
+    pub fn estimate_cost(&self, text: &str) -> Result<CostEstimate> {
+        let input_tokens = spending::estimate_tokens(&self.build_system_prompt())
+            + spending::estimate_tokens(text)
+            // Wrapper text around the user prompt
+            + 10;
+        // The JSON reply repeats roughly the length of the input, capped by max_tokens
+        let output_tokens = (spending::estimate_tokens(text) + 20).min(MAX_OUTPUT_TOKENS);
+
+        spending::estimate_cost(
+            &self.config,
+            self.model_name(),
+            TokenUsage {
+                prompt_tokens: input_tokens,
+                completion_tokens: output_tokens,
+                total_tokens: input_tokens + output_tokens,
+            },
+        )
+    }
+
+    /// Name of the model that serves requests for the configured provider
+    pub fn model_name(&self) -> &str {
+        if self.config.api_provider == "azure_openai" {
+            &self.config.azure_deployment_name
+        } else {
+            &self.config.model
+        }
+    }
+
     async fn call_openai(&self, request_body: Value) -> Result<Value> {
         let url = "https://api.openai.com/v1/chat/completions";
 
@@ -370,8 +603,10 @@ impl TranslationService {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     DuplicateRequest,
+    BudgetExceeded(BudgetExceeded),
     ApiError(anyhow::Error),
 }
 
@@ -379,6 +614,7 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::DuplicateRequest => write!(f, "Duplicate request"),
+            Error::BudgetExceeded(e) => write!(f, "{}", e),
             Error::ApiError(e) => write!(f, "API error: {}", e),
         }
     }
@@ -392,16 +628,66 @@ impl From<anyhow::Error> for Error {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    DuplicateRequest,
+    BudgetExceeded,
+    Api,
+    Clipboard, // No image in the clipboard, or it could not be read
+    Ocr,       // Tesseract failed or found no text
+}
+
+/// Error returned by the translation commands, so the frontend can tell a blocked
+/// budget from a failed request
+#[derive(Debug, Clone, Serialize)]
+pub struct TranslationFailure {
+    pub kind: FailureKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetExceeded>, // Set for BudgetExceeded
+}
+
+impl TranslationFailure {
+    pub fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            budget: None,
+        }
+    }
+}
+
+impl From<Error> for TranslationFailure {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::DuplicateRequest => {
+                TranslationFailure::new(FailureKind::DuplicateRequest, message)
+            }
+            Error::BudgetExceeded(exceeded) => TranslationFailure {
+                kind: FailureKind::BudgetExceeded,
+                message,
+                budget: Some(exceeded),
+            },
+            Error::ApiError(_) => TranslationFailure::new(FailureKind::Api, message),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranslationResponse {
     pub original_text: String,
     pub translated_text: String,
     pub detected_language: String,
     pub target_language: String,
+    pub metadata: TranslationMetadata,
+    pub budget_warning: Option<String>,
 }
 
 pub async fn translate_text(
     text: String,
+    source: CaptureSource,
     config: tauri::State<'_, crate::AppState>,
 ) -> Result<TranslationResponse, Error> {
     log::info!("translate_text called with text: {}", text);
@@ -410,30 +696,169 @@ pub async fn translate_text(
     let config_clone = config_guard.clone();
     drop(config_guard);
 
-    log::info!(
-        "Config loaded, custom_prompt: {}",
-        config_clone.custom_prompt
-    );
+    translate_with_config(text, source, config_clone).await
+}
+
+pub fn check_unlocked() -> Result<(), Error> {
+    let status = crate::vault::status();
+    if status.enabled && !status.unlocked {
+        return Err(Error::ApiError(anyhow::anyhow!(
+            "Encrypted storage is locked, unlock it to translate"
+        )));
+    }
+    Ok(())
+}
+
+fn into_response(
+    service: &TranslationService,
+    original_text: String,
+    source: CaptureSource,
+    result: TranslationResult,
+) -> TranslationResponse {
+    // Use the configured target language from config
+    let target_language = service.config.target_language.clone();
+
+    let mut metadata = result.metadata;
+    metadata.capture_source = source;
+
+    TranslationResponse {
+        original_text,
+        translated_text: result.translated_text,
+        detected_language: result.detected_language,
+        target_language,
+        metadata,
+        budget_warning: result.budget_warning,
+    }
+}
+
+fn into_error(e: anyhow::Error) -> Error {
+    if let Some(exceeded) = e.downcast_ref::<BudgetExceeded>() {
+        Error::BudgetExceeded(exceeded.clone())
+    } else if e.to_string().contains("Duplicate request detected") {
+        Error::DuplicateRequest
+    } else {
+        Error::ApiError(e)
+    }
+}
+
+pub async fn translate_with_config(
+    text: String,
+    source: CaptureSource,
+    config: Config,
+) -> Result<TranslationResponse, Error> {
+    check_unlocked()?;
+
+    log::info!("Config loaded, custom_prompt: {}", config.custom_prompt);
 
-    let service = TranslationService::new(config_clone);
+    let service = TranslationService::new(config);
     match service.detect_and_translate(&text).await {
-        Ok(result) => {
-            // Use the configured target language from config
-            let target_language = service.config.target_language.clone();
-
-            Ok(TranslationResponse {
-                original_text: text,
-                translated_text: result.translated_text,
-                detected_language: result.detected_language,
-                target_language,
+        Ok(result) => Ok(into_response(&service, text, source, result)),
+        Err(e) => Err(into_error(e)),
+    }
+}
+
+/// Translates a PNG image with the configured model, which has to accept images. The
+/// text the model read from the image becomes the original text
+pub async fn translate_image_with_config(
+    png: &[u8],
+    config: Config,
+) -> Result<TranslationResponse, Error> {
+    check_unlocked()?;
+
+    let service = TranslationService::new(config);
+    match service.translate_image(png).await {
+        Ok(mut result) => Ok(into_response(
+            &service,
+            result
+                .source_text
+                .take()
+                .unwrap_or_else(|| "[Image]".to_string()),
+            CaptureSource::Image,
+            result,
+        )),
+        Err(e) => Err(into_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(custom_prompt: &str) -> TranslationService {
+        TranslationService::new(Config {
+            api_provider: "openai".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            custom_prompt: custom_prompt.to_string(),
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn asks_to_keep_segment_markers() {
+        let prompt = service("Translate").build_system_prompt();
+        assert!(prompt.contains("keep every marker exactly as written and in the same order"));
+    }
+
+    #[test]
+    fn hashes_the_system_prompt() {
+        let hash = |custom_prompt| prompt_hash(&service(custom_prompt).build_system_prompt());
+        assert_eq!(hash("Translate"), hash("Translate"));
+        assert_ne!(hash("Translate"), hash("Translate formally"));
+        assert_eq!(hash("Translate").len(), 64);
+    }
+
+    #[test]
+    fn records_usage_and_latency_from_the_response() {
+        let service = service("Translate");
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "content": "{\"detected_language\": \"Norwegian\", \"translated_text\": \"Hello\"}"
+                }
+            }],
+            "usage": { "prompt_tokens": 120, "completion_tokens": 8, "total_tokens": 128 }
+        });
+        let result = service
+            .parse_completion(&response, "abc".to_string(), 420)
+            .unwrap();
+
+        assert_eq!(result.translated_text, "Hello");
+        assert_eq!(result.detected_language, "Norwegian");
+        let metadata = result.metadata;
+        assert_eq!(metadata.provider, "openai");
+        assert_eq!(metadata.model, "gpt-4o-mini");
+        assert_eq!(metadata.prompt_hash, "abc");
+        assert_eq!(metadata.latency_ms, 420);
+        assert_eq!(
+            metadata.usage,
+            Some(TokenUsage {
+                prompt_tokens: 120,
+                completion_tokens: 8,
+                total_tokens: 128,
             })
-        }
-        Err(e) => {
-            if e.to_string().contains("Duplicate request detected") {
-                Err(Error::DuplicateRequest)
-            } else {
-                Err(Error::ApiError(e))
-            }
-        }
+        );
+        assert!(!metadata.used_json_fallback);
+        assert_eq!(result.source_text, None);
+
+        // Image replies carry the text the model read
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "content": "{\"detected_language\": \"Norwegian\", \"translated_text\": \"Exit\", \"source_text\": \"Utgang\"}"
+                }
+            }]
+        });
+        let result = service
+            .parse_completion(&response, "abc".to_string(), 10)
+            .unwrap();
+        assert_eq!(result.source_text.as_deref(), Some("Utgang"));
+
+        // A reply without a usage block still translates
+        let response = json!({ "choices": [{ "message": { "content": "Hello" } }] });
+        let result = service
+            .parse_completion(&response, "abc".to_string(), 10)
+            .unwrap();
+        assert_eq!(result.metadata.usage, None);
+        assert!(result.metadata.used_json_fallback);
     }
 }
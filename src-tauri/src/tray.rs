@@ -1,13 +1,23 @@
 use anyhow::Result;
 use tauri::{
     image::Image,
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager,
+    AppHandle, Manager, Wry,
 };
 
+use crate::clipboard_watcher;
+use crate::config::Config;
+use crate::profiles;
 use crate::theme::{get_system_theme, SystemTheme};
 
+const TRAY_ID: &str = "main-tray";
+
+// Menu ids of profile entries are the profile name behind this prefix
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+const CLIPBOARD_WATCH_ITEM: &str = "clipboard-watch";
+
 fn get_optimal_icon_size() -> u32 {
     // On Windows, try to detect DPI scaling
     #[cfg(target_os = "windows")]
@@ -68,14 +78,63 @@ fn get_theme_icon() -> Result<Image<'static>> {
     Image::from_bytes(icon_bytes).map_err(|e| anyhow::anyhow!("Failed to load tray icon: {}", e))
 }
 
-pub fn create_tray(app: &AppHandle) -> Result<()> {
-    log::info!("Starting tray creation...");
+fn build_profile_menu(app: &AppHandle, active_profile: Option<&str>) -> Result<Submenu<Wry>> {
+    let profiles = profiles::list_profiles(active_profile).unwrap_or_else(|e| {
+        log::error!("Failed to read profiles for the tray menu: {}", e);
+        Vec::new()
+    });
 
-    // Create tray menu
+    let submenu = Submenu::with_id(app, "profiles", "Profile", true)?;
+    if profiles.is_empty() {
+        let empty = MenuItem::with_id(app, "no-profiles", "No profiles", false, None::<&str>)?;
+        submenu.append(&empty)?;
+    }
+    for profile in profiles {
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROFILE_ITEM_PREFIX, profile.name),
+            &profile.name,
+            true,
+            profile.active,
+            None::<&str>,
+        )?;
+        submenu.append(&item)?;
+    }
+    Ok(submenu)
+}
+
+fn build_menu(app: &AppHandle, config: &Config) -> Result<Menu<Wry>> {
     let open_item = MenuItem::with_id(app, "open", "Open GPTranslate", true, None::<&str>)?;
+    let profile_menu = build_profile_menu(app, config.active_profile.as_deref())?;
+    // Only usable once clipboard watching is turned on in the settings
+    let watch_enabled = config.clipboard_watch.enabled;
+    let watch_item = CheckMenuItem::with_id(
+        app,
+        CLIPBOARD_WATCH_ITEM,
+        "Watch clipboard",
+        watch_enabled,
+        watch_enabled && !clipboard_watcher::is_paused(),
+        None::<&str>,
+    )?;
     let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
-    let menu = Menu::with_items(app, &[&open_item, &quit_item])?;
+    let items: [&dyn IsMenuItem<Wry>; 4] = [&open_item, &profile_menu, &watch_item, &quit_item];
+    Ok(Menu::with_items(app, &items)?)
+}
+
+/// Rebuilds the tray menu after profiles or clipboard watching changed
+pub fn refresh_menu(app: &AppHandle, config: &Config) -> Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(build_menu(app, config)?))?;
+    }
+    Ok(())
+}
+
+pub fn create_tray(app: &AppHandle, config: &Config) -> Result<()> {
+    log::info!("Starting tray creation...");
+
+    // Create tray menu
+    let menu = build_menu(app, config)?;
 
     log::info!("Tray menu created successfully");
 
@@ -95,7 +154,7 @@ pub fn create_tray(app: &AppHandle) -> Result<()> {
     };
 
     // Create tray icon
-    let _tray = TrayIconBuilder::with_id("main-tray")
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .tooltip("GPTranslate")
         .icon(icon)
         .menu(&menu)
@@ -107,10 +166,31 @@ pub fn create_tray(app: &AppHandle) -> Result<()> {
                     window.set_focus().unwrap();
                 }
             }
+            CLIPBOARD_WATCH_ITEM => {
+                // The check mark toggles itself when clicked
+                clipboard_watcher::toggle_paused();
+            }
             "quit" => {
                 app.exit(0);
             }
-            _ => {}
+            id => {
+                if let Some(name) = id.strip_prefix(PROFILE_ITEM_PREFIX) {
+                    let app = app.clone();
+                    let name = name.to_string();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::switch_profile(&app, &name).await {
+                            log::error!("Failed to switch to profile \"{}\": {}", name, e);
+                            // The click already moved the check mark, put it back on the
+                            // profile that is still active
+                            let state = app.state::<crate::AppState>();
+                            let config = state.config.lock().await.clone();
+                            if let Err(e) = refresh_menu(&app, &config) {
+                                log::error!("Failed to update tray menu: {}", e);
+                            }
+                        }
+                    });
+                }
+            }
         })
         .on_tray_icon_event(|tray, event| {
             if let Some(app) = tray.app_handle().get_webview_window("main") {
@@ -0,0 +1,297 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+// Encrypted values are stored as "enc:v1:<base64 of nonce followed by ciphertext>"
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+// Known plaintext encrypted with the derived key, used to check the passphrase on unlock
+const VERIFIER_PLAINTEXT: &str = "gptranslate-vault";
+
+lazy_static! {
+    static ref VAULT: Mutex<VaultState> = Mutex::new(VaultState::default());
+}
+
+#[derive(Default)]
+struct VaultState {
+    header: Option<EncryptionHeader>,
+    key: Option<Key>,
+}
+
+// Wiped from memory when dropped
+type Key = Zeroizing<[u8; KEY_LEN]>;
+
+/// Key derivation parameters stored in the config so the key can be re-derived on unlock
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EncryptionHeader {
+    pub salt: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+    pub verifier: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+}
+
+fn derive_key(passphrase: &str, header: &EncryptionHeader) -> Result<Key> {
+    let salt = BASE64.decode(&header.salt)?;
+    let params = Params::new(header.m_cost, header.t_cost, header.p_cost, Some(KEY_LEN))
+        .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+// Key derivation is deliberately slow, so it runs on a blocking thread instead of
+// holding up an async runtime worker
+async fn derive_key_blocking(passphrase: &str, header: &EncryptionHeader) -> Result<Key> {
+    let passphrase = Zeroizing::new(passphrase.to_string());
+    let header = header.clone();
+    tauri::async_runtime::spawn_blocking(move || derive_key(&passphrase, &header))
+        .await
+        .map_err(|e| anyhow::anyhow!("Key derivation was interrupted: {}", e))?
+}
+
+fn encrypt_with(key: &[u8; KEY_LEN], plaintext: &str) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+}
+
+fn decrypt_with(key: &[u8; KEY_LEN], value: &str) -> Result<String> {
+    let encoded = value
+        .trim()
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| anyhow::anyhow!("Value is not encrypted"))?;
+    let payload = BASE64.decode(encoded)?;
+    if payload.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("Encrypted value is truncated"));
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failed: wrong passphrase or corrupted data"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Sets up the vault from the header found in the loaded config; the vault starts locked
+pub fn init(header: Option<EncryptionHeader>) {
+    let mut vault = VAULT.lock().unwrap();
+    vault.header = header;
+    vault.key = None;
+}
+
+pub fn status() -> EncryptionStatus {
+    let vault = VAULT.lock().unwrap();
+    EncryptionStatus {
+        enabled: vault.header.is_some(),
+        unlocked: vault.key.is_some(),
+    }
+}
+
+pub fn header() -> Option<EncryptionHeader> {
+    VAULT.lock().unwrap().header.clone()
+}
+
+pub fn is_enabled() -> bool {
+    VAULT.lock().unwrap().header.is_some()
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.trim_start().starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Creates a new header and key from the passphrase and leaves the vault unlocked
+pub async fn create(passphrase: &str) -> Result<EncryptionHeader> {
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("Passphrase must not be empty"));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut header = EncryptionHeader {
+        salt: BASE64.encode(salt),
+        m_cost: Params::DEFAULT_M_COST,
+        t_cost: Params::DEFAULT_T_COST,
+        p_cost: Params::DEFAULT_P_COST,
+        verifier: String::new(),
+    };
+    let key = derive_key_blocking(passphrase, &header).await?;
+    header.verifier = encrypt_with(&key, VERIFIER_PLAINTEXT)?;
+
+    let mut vault = VAULT.lock().unwrap();
+    vault.header = Some(header.clone());
+    vault.key = Some(key);
+    Ok(header)
+}
+
+pub async fn unlock(passphrase: &str) -> Result<()> {
+    let header = VAULT
+        .lock()
+        .unwrap()
+        .header
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Encryption is not enabled"))?;
+
+    // Key derivation is deliberately slow, so it runs without holding the lock
+    let key = derive_key_blocking(passphrase, &header).await?;
+    match decrypt_with(&key, &header.verifier) {
+        Ok(check) if check == VERIFIER_PLAINTEXT => {}
+        _ => return Err(anyhow::anyhow!("Incorrect passphrase")),
+    }
+
+    VAULT.lock().unwrap().key = Some(key);
+    log::info!("Encrypted storage unlocked");
+    Ok(())
+}
+
+pub fn lock() {
+    VAULT.lock().unwrap().key = None;
+    log::info!("Encrypted storage locked");
+}
+
+/// Turns encryption off; callers must have decrypted everything they want to keep first
+pub fn disable() {
+    let mut vault = VAULT.lock().unwrap();
+    vault.header = None;
+    vault.key = None;
+}
+
+fn current_key() -> Result<Key> {
+    VAULT
+        .lock()
+        .unwrap()
+        .key
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Encrypted storage is locked"))
+}
+
+pub fn encrypt(plaintext: &str) -> Result<String> {
+    encrypt_with(&*current_key()?, plaintext)
+}
+
+pub fn decrypt(value: &str) -> Result<String> {
+    decrypt_with(&*current_key()?, value)
+}
+
+/// Encrypts the plaintext secrets in place when encryption is enabled. A new secret
+/// cannot be saved while the vault is locked
+pub fn encrypt_secrets<'a>(secrets: impl IntoIterator<Item = &'a mut String>) -> Result<()> {
+    let key = {
+        let vault = VAULT.lock().unwrap();
+        if vault.header.is_none() {
+            return Ok(());
+        }
+        vault.key.clone()
+    };
+    seal_secrets(key.as_deref(), secrets)
+}
+
+fn seal_secrets<'a>(
+    key: Option<&[u8; KEY_LEN]>,
+    secrets: impl IntoIterator<Item = &'a mut String>,
+) -> Result<()> {
+    for secret in secrets {
+        if secret.is_empty() || is_encrypted(secret) {
+            continue;
+        }
+        let key = key.ok_or_else(|| {
+            anyhow::anyhow!("Encrypted storage is locked, unlock it before saving a new API key")
+        })?;
+        *secret = encrypt_with(key, secret)?;
+    }
+    Ok(())
+}
+
+/// Replaces encrypted secrets with their plaintext; requires an unlocked vault
+pub fn decrypt_secrets<'a>(secrets: impl IntoIterator<Item = &'a mut String>) -> Result<()> {
+    for secret in secrets {
+        if is_encrypted(secret) {
+            *secret = decrypt(secret)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The smallest Argon2 cost, so the tests do not spend seconds deriving keys
+    fn cheap_header() -> EncryptionHeader {
+        EncryptionHeader {
+            salt: BASE64.encode([7u8; 16]),
+            m_cost: Params::MIN_M_COST,
+            t_cost: 1,
+            p_cost: 1,
+            verifier: String::new(),
+        }
+    }
+
+    #[test]
+    fn encrypts_and_decrypts_with_the_passphrase_key() {
+        let header = cheap_header();
+        let key = derive_key("correct horse", &header).unwrap();
+
+        let encrypted = encrypt_with(&key, "sk-secret").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("sk-secret"));
+        assert_eq!(decrypt_with(&key, &encrypted).unwrap(), "sk-secret");
+        // A fresh nonce every time
+        assert_ne!(encrypt_with(&key, "sk-secret").unwrap(), encrypted);
+
+        let wrong = derive_key("wrong horse", &header).unwrap();
+        assert!(decrypt_with(&wrong, &encrypted).is_err());
+        let truncated = &encrypted[..ENCRYPTED_PREFIX.len() + 8];
+        assert!(decrypt_with(&key, truncated).is_err());
+    }
+
+    #[test]
+    fn leaves_secrets_alone_without_encryption() {
+        let mut secret = "sk-plain".to_string();
+        let mut empty = String::new();
+        encrypt_secrets([&mut secret, &mut empty]).unwrap();
+        assert_eq!(secret, "sk-plain");
+        assert_eq!(empty, "");
+    }
+
+    #[test]
+    fn refuses_new_secrets_while_locked() {
+        let key = derive_key("correct horse", &cheap_header()).unwrap();
+        let mut stored = encrypt_with(&key, "sk-old").unwrap();
+        let before = stored.clone();
+        seal_secrets(None, [&mut stored]).unwrap();
+        assert_eq!(stored, before);
+
+        let mut new = "sk-new".to_string();
+        assert!(seal_secrets(None, [&mut new]).is_err());
+        assert_eq!(new, "sk-new");
+        seal_secrets(Some(&key), [&mut new]).unwrap();
+        assert_eq!(decrypt_with(&key, &new).unwrap(), "sk-new");
+    }
+}